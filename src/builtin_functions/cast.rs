@@ -1,7 +1,9 @@
+use crate::core::complex::Complex;
 use crate::core::error::Error;
 use crate::core::object::Object;
 use crate::core::parser::Position;
 use std::collections::BTreeMap;
+use std::num::IntErrorKind;
 
 pub fn get_type(
     args: Vec<Object>,
@@ -15,7 +17,29 @@ pub fn get_type(
             pos,
         ))
     } else {
-        Ok(Object::Str(args[0].type_name().to_string()))
+        Ok(Object::Str(args[0].type_name().to_string().into()))
+    }
+}
+
+// name of the variant of enum, or the name of struct
+pub fn get_variant(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    if !kwargs.is_empty() || args.len() != 1 {
+        Err(Error::ArgumentError(
+            "function `variant` takes only one argument.".to_string(),
+            pos,
+        ))
+    } else {
+        match &args[0] {
+            Object::Instance { def, .. } => Ok(Object::Str(def.name.clone().into())),
+            _ => Err(Error::TypeError(
+                format!("{} is not an enum or struct value", args[0].type_name()),
+                pos,
+            )),
+        }
     }
 }
 
@@ -33,7 +57,7 @@ pub fn to_str(
     } else {
         match &args[0] {
             Object::Str(s) => Ok(Object::Str(s.clone())),
-            _ => Ok(Object::Str(args[0].to_string())),
+            _ => Ok(Object::Str(args[0].to_text(pos)?.into())),
         }
     }
 }
@@ -52,18 +76,41 @@ pub fn to_int(
     } else {
         match args[0].clone() {
             Object::Int(i) => Ok(Object::Int(i)),
-            Object::Float(f) => Ok(Object::Int(f as i64)),
-            Object::Str(s) => {
-                let parse_result = s.parse::<i64>();
-                if let Ok(i) = parse_result {
-                    Ok(Object::Int(i))
-                } else {
-                    Err(Error::ParseError(
-                        format!("failed to parse `{}` as int", &args[0].type_name()),
+            Object::Float(f) => {
+                // `as` saturates, so check the range before conversion.
+                if f.is_nan() {
+                    Err(Error::ValueError(
+                        "cannot convert NaN to int".to_string(),
+                        pos,
+                    ))
+                } else if f >= -(i64::MIN as f64) || f < i64::MIN as f64 {
+                    Err(Error::OverflowError(
+                        format!("{} is out of range of int", f),
                         pos,
                     ))
+                } else {
+                    Ok(Object::Int(f as i64))
                 }
             }
+            Object::Str(s) => match s.parse::<i64>() {
+                Ok(i) => Ok(Object::Int(i)),
+                // a well-formed integer which does not fit in int
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow
+                    ) =>
+                {
+                    Err(Error::OverflowError(
+                        format!("{} is out of range of int", s),
+                        pos,
+                    ))
+                }
+                Err(_) => Err(Error::ParseError(
+                    format!("failed to parse `{}` as int", &args[0].type_name()),
+                    pos,
+                )),
+            },
             _ => Err(Error::TypeError(
                 format!("cannot convert {} to int", args[0].type_name()),
                 pos,
@@ -105,3 +152,31 @@ pub fn to_float(
         }
     }
 }
+
+// make complex number from real and imaginary parts
+pub fn to_complex(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    if !kwargs.is_empty() || args.is_empty() || args.len() > 2 {
+        return Err(Error::ArgumentError(
+            "function `complex` takes one or two arguments.".to_string(),
+            pos,
+        ));
+    }
+    let mut parts = vec![];
+    for arg in args.iter() {
+        match arg.to_complex() {
+            Some(part) if args.len() == 1 || part.im == 0.0 => parts.push(part),
+            _ => {
+                return Err(Error::TypeError(
+                    format!("cannot convert {} to complex", arg.type_name()),
+                    pos,
+                ))
+            }
+        }
+    }
+    let im = parts.get(1).map_or(0.0, |part| part.re);
+    Ok(Object::Complex(Complex::new(parts[0].re, parts[0].im + im)))
+}
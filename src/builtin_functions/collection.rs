@@ -0,0 +1,173 @@
+use crate::core::error::Error;
+use crate::core::object::Object;
+use crate::core::parser::Position;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+pub fn len(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    // this function takes only one argument.
+    if !kwargs.is_empty() || args.len() != 1 {
+        Err(Error::ArgumentError(
+            "function `len` takes only one argument.".to_string(),
+            pos,
+        ))
+    } else if let Some(result) = args[0].call_special("__len__", vec![], pos) {
+        result
+    } else {
+        match &args[0] {
+            Object::Str(s) => Ok(Object::Int(s.char_count() as i64)),
+            Object::List(l, _) | Object::Tuple(l) => Ok(Object::Int(l.len() as i64)),
+            Object::Set(table, _) | Object::Hash(table, _) => Ok(Object::Int(table.len() as i64)),
+            Object::Range(start, end) if end <= start => Ok(Object::Int(0)),
+            // the length of a range such as `-2**63..2**63-1` does not fit in int
+            Object::Range(start, end) => {
+                end.checked_sub(*start).map(Object::Int).ok_or_else(|| {
+                    Error::OverflowError(
+                        format!("len({}..{}) is out of range of int", start, end),
+                        pos,
+                    )
+                })
+            }
+            _ => Err(Error::TypeError(
+                format!("cannot calculate len({})", args[0].type_name()),
+                pos,
+            )),
+        }
+    }
+}
+
+// sort objects by `Object::compare`. the sort is stable, and equal elements keep their order
+// even if reversed.
+fn sort_objects(objects: &mut [Object], reverse: bool, pos: Position) -> Result<(), Error> {
+    let mut error = None;
+    objects.sort_by(|a, b| {
+        let (a, b) = if reverse { (b, a) } else { (a, b) };
+        match a.compare(b, pos) {
+            Ok(ordering) => ordering,
+            Err(e) => {
+                error.get_or_insert(e);
+                Ordering::Equal
+            }
+        }
+    });
+    match error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+// returns sorted copy of list
+pub fn sort(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    if args.len() != 1 || kwargs.keys().any(|k| k != "reverse") {
+        return Err(Error::ArgumentError(
+            "function `sort` takes one argument and keyword argument `reverse`.".to_string(),
+            pos,
+        ));
+    }
+    match args[0].clone() {
+        Object::List(mut l, _) => {
+            let elements: &mut Vec<Object> = Rc::make_mut(&mut l);
+            let reverse = kwargs.get("reverse").is_some_and(|r| r.clone().to_bool());
+            sort_objects(elements, reverse, pos)?;
+            Ok(Object::List(l, false))
+        }
+        _ => Err(Error::TypeError(
+            format!("cannot sort {}", args[0].type_name()),
+            pos,
+        )),
+    }
+}
+
+// `min(list)` or `min(a, b, ...)`
+fn extremum(
+    name: &str,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    wanted: Ordering,
+    pos: Position,
+) -> Result<Object, Error> {
+    if !kwargs.is_empty() || args.is_empty() {
+        return Err(Error::ArgumentError(
+            format!("function `{}` takes at least one argument.", name),
+            pos,
+        ));
+    }
+    let candidates = match (args.len(), &args[0]) {
+        (1, Object::List(l, _)) => l.to_vec(),
+        _ => args,
+    };
+    let mut best: Option<Object> = None;
+    for candidate in candidates {
+        best = match best {
+            Some(b) if candidate.compare(&b, pos)? != wanted => Some(b),
+            _ => Some(candidate),
+        };
+    }
+    best.ok_or(Error::ValueError(
+        format!("function `{}` takes non-empty list", name),
+        pos,
+    ))
+}
+
+pub fn min(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    extremum("min", args, kwargs, Ordering::Less, pos)
+}
+
+pub fn max(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    extremum("max", args, kwargs, Ordering::Greater, pos)
+}
+
+// reversed copy of str, list or tuple. str is reversed by chars.
+pub fn reverse(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    if args.len() != 1 || !kwargs.is_empty() {
+        return Err(Error::ArgumentError(
+            "function `reverse` takes only one argument.".to_string(),
+            pos,
+        ));
+    }
+    match &args[0] {
+        Object::Str(s) => Ok(Object::Str(s.chars().rev().collect::<String>().into())),
+        Object::List(l, _) => Ok(Object::new_list(l.iter().rev().cloned().collect())),
+        Object::Tuple(l) => Ok(Object::new_tuple(l.iter().rev().cloned().collect())),
+        _ => Err(Error::TypeError(
+            format!("cannot reverse {}", args[0].type_name()),
+            pos,
+        )),
+    }
+}
+
+// copy of the value which cannot be modified, including its elements
+pub fn freeze(
+    mut args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    if args.len() != 1 || !kwargs.is_empty() {
+        return Err(Error::ArgumentError(
+            "function `freeze` takes only one argument.".to_string(),
+            pos,
+        ));
+    }
+    Ok(args.remove(0).freeze())
+}
@@ -0,0 +1,296 @@
+use crate::core::error::Error;
+use crate::core::object::Object;
+use crate::core::parser::Position;
+use std::collections::BTreeMap;
+
+// width and precision larger than this are rejected, rather than allocating huge strings
+const MAX_WIDTH: usize = 10000;
+
+// format specification: [[fill]align][sign][#][0][width][,|_][.precision][type]
+struct FormatSpec {
+    fill: char,
+    align: Option<char>,
+    sign: char,
+    alternate: bool,
+    zero_padding: bool,
+    width: usize,
+    grouping: Option<char>,
+    precision: Option<usize>,
+    kind: Option<char>,
+}
+
+impl FormatSpec {
+    fn parse(spec: &str, pos: Position) -> Result<Self, Error> {
+        let chars = spec.chars().collect::<Vec<char>>();
+        let mut i = 0;
+        let mut out = FormatSpec {
+            fill: ' ',
+            align: None,
+            sign: '-',
+            alternate: false,
+            zero_padding: false,
+            width: 0,
+            grouping: None,
+            precision: None,
+            kind: None,
+        };
+        let is_align = |c: char| c == '<' || c == '>' || c == '^';
+        if chars.len() >= 2 && is_align(chars[1]) {
+            out.fill = chars[0];
+            out.align = Some(chars[1]);
+            i = 2;
+        } else if !chars.is_empty() && is_align(chars[0]) {
+            out.align = Some(chars[0]);
+            i = 1;
+        }
+        if i < chars.len() && "+- ".contains(chars[i]) {
+            out.sign = chars[i];
+            i += 1;
+        }
+        if i < chars.len() && chars[i] == '#' {
+            out.alternate = true;
+            i += 1;
+        }
+        if i < chars.len() && chars[i] == '0' {
+            out.zero_padding = true;
+            i += 1;
+        }
+        let width_begin = i;
+        while i < chars.len() && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+        if i > width_begin {
+            out.width = parse_size("width", &chars[width_begin..i], pos)?;
+        }
+        if i < chars.len() && (chars[i] == ',' || chars[i] == '_') {
+            out.grouping = Some(chars[i]);
+            i += 1;
+        }
+        if i < chars.len() && chars[i] == '.' {
+            i += 1;
+            let precision_begin = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            if i == precision_begin {
+                return Err(Error::ArgumentError(
+                    format!("invalid format spec `{}`", spec),
+                    pos,
+                ));
+            }
+            out.precision = Some(parse_size("precision", &chars[precision_begin..i], pos)?);
+        }
+        if i < chars.len() && "sdfexXbo%".contains(chars[i]) {
+            out.kind = Some(chars[i]);
+            i += 1;
+        }
+        if i != chars.len() {
+            return Err(Error::ArgumentError(
+                format!("invalid format spec `{}`", spec),
+                pos,
+            ));
+        }
+        Ok(out)
+    }
+
+    // pad `body` to the width. `prefix` (sign and radix prefix) is kept before zero padding.
+    fn pad(&self, prefix: &str, body: &str, numeric: bool) -> String {
+        let length = prefix.chars().count() + body.chars().count();
+        if length >= self.width {
+            return format!("{}{}", prefix, body);
+        }
+        let padding = self.width - length;
+        if self.zero_padding && self.align.is_none() && numeric {
+            return format!("{}{}{}", prefix, "0".repeat(padding), body);
+        }
+        let fill = |n: usize| self.fill.to_string().repeat(n);
+        let default_align = if numeric { '>' } else { '<' };
+        match self.align.unwrap_or(default_align) {
+            '<' => format!("{}{}{}", prefix, body, fill(padding)),
+            '^' => format!(
+                "{}{}{}{}",
+                fill(padding / 2),
+                prefix,
+                body,
+                fill(padding - padding / 2)
+            ),
+            _ => format!("{}{}{}", fill(padding), prefix, body),
+        }
+    }
+
+    fn sign_of(&self, negative: bool) -> &str {
+        match (negative, self.sign) {
+            (true, _) => "-",
+            (false, '+') => "+",
+            (false, ' ') => " ",
+            _ => "",
+        }
+    }
+}
+
+// width or precision, which is not larger than `MAX_WIDTH`
+fn parse_size(name: &str, digits: &[char], pos: Position) -> Result<usize, Error> {
+    match digits.iter().collect::<String>().parse::<usize>() {
+        Ok(size) if size <= MAX_WIDTH => Ok(size),
+        _ => Err(Error::ValueError(
+            format!("{} of format spec must be at most {}", name, MAX_WIDTH),
+            pos,
+        )),
+    }
+}
+
+// insert separators into a string of digits, every `every` digits from the right.
+fn group_digits(digits: &str, separator: Option<char>, every: usize) -> String {
+    match separator {
+        None => digits.to_string(),
+        Some(separator) => {
+            let mut out = String::new();
+            for (i, c) in digits.chars().enumerate() {
+                if i > 0 && (digits.len() - i).is_multiple_of(every) {
+                    out.push(separator);
+                }
+                out.push(c);
+            }
+            out
+        }
+    }
+}
+
+fn format_int(i: i64, spec: &FormatSpec, pos: Position) -> Result<String, Error> {
+    let magnitude = i.unsigned_abs();
+    // digits of binary, octal and hex are grouped by four with `_`
+    if spec.grouping == Some(',') && matches!(spec.kind, Some('x' | 'X' | 'o' | 'b')) {
+        return Err(Error::ValueError(
+            format!("`,` cannot be used with format `{}`", spec.kind.unwrap()),
+            pos,
+        ));
+    }
+    let (radix_prefix, digits) = match spec.kind {
+        None | Some('d') => ("", group_digits(&magnitude.to_string(), spec.grouping, 3)),
+        Some('x') => ("0x", format!("{:x}", magnitude)),
+        Some('X') => ("0X", format!("{:X}", magnitude)),
+        Some('o') => ("0o", format!("{:o}", magnitude)),
+        Some('b') => ("0b", format!("{:b}", magnitude)),
+        Some('f') | Some('e') | Some('%') => return format_float(i as f64, spec, pos),
+        Some(kind) => {
+            return Err(Error::TypeError(
+                format!("format `{}` cannot be used with int", kind),
+                pos,
+            ))
+        }
+    };
+    let digits = match spec.kind {
+        None | Some('d') => digits,
+        _ => group_digits(&digits, spec.grouping, 4),
+    };
+    let mut prefix = spec.sign_of(i < 0).to_string();
+    if spec.alternate {
+        prefix.push_str(radix_prefix);
+    }
+    Ok(spec.pad(&prefix, &digits, true))
+}
+
+fn format_float(f: f64, spec: &FormatSpec, pos: Position) -> Result<String, Error> {
+    let magnitude = f.abs();
+    let body = match spec.kind {
+        None => match spec.precision {
+            Some(precision) => format!("{:.*}", precision, magnitude),
+            None => Object::Float(magnitude).to_string(),
+        },
+        Some('f') => format!("{:.*}", spec.precision.unwrap_or(6), magnitude),
+        Some('%') => format!("{:.*}%", spec.precision.unwrap_or(6), magnitude * 100.0),
+        Some('e') => {
+            let s = format!("{:.*e}", spec.precision.unwrap_or(6), magnitude);
+            let (mantissa, exponent) = s.split_once('e').unwrap();
+            let exponent = exponent.parse::<i32>().unwrap();
+            let exponent_sign = if exponent < 0 { '-' } else { '+' };
+            format!("{}e{}{:02}", mantissa, exponent_sign, exponent.abs())
+        }
+        Some(kind) => {
+            return Err(Error::TypeError(
+                format!("format `{}` cannot be used with float", kind),
+                pos,
+            ))
+        }
+    };
+    // group digits of integer part
+    let body = if spec.kind != Some('e') && magnitude.is_finite() {
+        let split = body
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(body.len());
+        format!(
+            "{}{}",
+            group_digits(&body[..split], spec.grouping, 3),
+            &body[split..]
+        )
+    } else {
+        body
+    };
+    Ok(spec.pad(spec.sign_of(f.is_sign_negative() && f != 0.0), &body, true))
+}
+
+fn format_str(s: &str, spec: &FormatSpec, pos: Position) -> Result<String, Error> {
+    match spec.kind {
+        None | Some('s') => {
+            let body = match spec.precision {
+                Some(precision) => s.chars().take(precision).collect::<String>(),
+                None => s.to_string(),
+            };
+            Ok(spec.pad("", &body, false))
+        }
+        Some(kind) => Err(Error::TypeError(
+            format!("format `{}` cannot be used with str", kind),
+            pos,
+        )),
+    }
+}
+
+fn format_with_spec(obj: &Object, spec: &FormatSpec, pos: Position) -> Result<String, Error> {
+    match obj {
+        Object::Int(i) => format_int(*i, spec, pos),
+        Object::Float(f) => format_float(*f, spec, pos),
+        Object::Str(s) => format_str(s, spec, pos),
+        // the spec is applied to each element of list
+        Object::List(l, _) => {
+            let mut elements = vec![];
+            for element in l.iter() {
+                elements.push(format_with_spec(element, spec, pos)?);
+            }
+            Ok(format!("[{}]", elements.join(", ")))
+        }
+        _ => format_str(&obj.to_text(pos)?, spec, pos),
+    }
+}
+
+// format object with format spec. str is not quoted.
+pub fn format_object(obj: &Object, spec: &str, pos: Position) -> Result<String, Error> {
+    if spec.is_empty() {
+        return obj.to_text(pos);
+    }
+    format_with_spec(obj, &FormatSpec::parse(spec, pos)?, pos)
+}
+
+pub fn format(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    if !kwargs.is_empty() || args.is_empty() || args.len() > 2 {
+        Err(Error::ArgumentError(
+            "function `format` takes one or two arguments.".to_string(),
+            pos,
+        ))
+    } else {
+        let spec = match args.get(1) {
+            Some(Object::Str(s)) => s.to_string(),
+            Some(obj) => {
+                return Err(Error::TypeError(
+                    format!("format spec must be str, given {}", obj.type_name()),
+                    pos,
+                ))
+            }
+            None => "".to_string(),
+        };
+        Ok(Object::Str(format_object(&args[0], &spec, pos)?.into()))
+    }
+}
@@ -0,0 +1,20 @@
+use crate::core::error::Error;
+use crate::core::gc;
+use crate::core::object::Object;
+use crate::core::parser::Position;
+use std::collections::BTreeMap;
+
+// `gc()` reclaims objects in reference cycles and returns the number of them
+pub fn gc(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    if !args.is_empty() || !kwargs.is_empty() {
+        return Err(Error::ArgumentError(
+            "function `gc` takes no arguments.".to_string(),
+            pos,
+        ));
+    }
+    Ok(Object::Int(gc::collect() as i64))
+}
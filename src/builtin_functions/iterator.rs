@@ -0,0 +1,197 @@
+use crate::builtin_functions::check_arguments;
+use crate::core::error::Error;
+use crate::core::iterator::IterState;
+use crate::core::object::Object;
+use crate::core::parser::Position;
+use std::collections::BTreeMap;
+
+fn expect_count(name: &str, obj: &Object, pos: Position) -> Result<usize, Error> {
+    match obj {
+        Object::Int(n) if *n >= 0 => Ok(*n as usize),
+        _ => Err(Error::ValueError(
+            format!("function `{}` takes non-negative int, not {}", name, obj),
+            pos,
+        )),
+    }
+}
+
+pub fn iter(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("iter", &args, &kwargs, 1..=1, pos)?;
+    args[0].iter(pos)
+}
+
+// `next(it)` fails when the iterator is exhausted, and `next(it, default)` returns default
+pub fn next(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("next", &args, &kwargs, 1..=2, pos)?;
+    match (args[0].next(pos)?, args.get(1)) {
+        (Some(element), _) => Ok(element),
+        (None, Some(default)) => Ok(default.clone()),
+        (None, None) => Err(Error::ValueError("iterator is exhausted".to_string(), pos)),
+    }
+}
+
+// all elements of the optional iterable argument
+fn collect(
+    name: &str,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Vec<Object>, Error> {
+    check_arguments(name, &args, &kwargs, 0..=1, pos)?;
+    let mut elements = vec![];
+    if let Some(iterable) = args.first() {
+        let iterator = iterable.iter(pos)?;
+        while let Some(element) = iterator.next(pos)? {
+            elements.push(element);
+        }
+    }
+    Ok(elements)
+}
+
+pub fn list(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    Ok(Object::new_list(collect("list", args, kwargs, pos)?))
+}
+
+pub fn tuple(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    Ok(Object::new_tuple(collect("tuple", args, kwargs, pos)?))
+}
+
+pub fn set(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    Object::new_set(collect("set", args, kwargs, pos)?, pos)
+}
+
+pub fn map(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("map", &args, &kwargs, 2..=2, pos)?;
+    Ok(Object::new_iterator(IterState::Map(
+        args[0].iter(pos)?,
+        args[1].clone(),
+    )))
+}
+
+pub fn filter(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("filter", &args, &kwargs, 2..=2, pos)?;
+    Ok(Object::new_iterator(IterState::Filter(
+        args[0].iter(pos)?,
+        args[1].clone(),
+    )))
+}
+
+pub fn take(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("take", &args, &kwargs, 2..=2, pos)?;
+    let count = expect_count("take", &args[1], pos)?;
+    Ok(Object::new_iterator(IterState::Take(
+        args[0].iter(pos)?,
+        count,
+    )))
+}
+
+pub fn skip(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("skip", &args, &kwargs, 2..=2, pos)?;
+    let count = expect_count("skip", &args[1], pos)?;
+    Ok(Object::new_iterator(IterState::Skip(
+        args[0].iter(pos)?,
+        count,
+    )))
+}
+
+// pairs of index and element
+pub fn enumerate(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("enumerate", &args, &kwargs, 1..=1, pos)?;
+    Ok(Object::new_iterator(IterState::Enumerate(
+        args[0].iter(pos)?,
+        0,
+    )))
+}
+
+// stops at the end of the shortest iterable
+pub fn zip(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("zip", &args, &kwargs, 1..=usize::MAX, pos)?;
+    let iterators = args
+        .iter()
+        .map(|arg| arg.iter(pos))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Object::new_iterator(IterState::Zip(iterators)))
+}
+
+// infinite sequence `start, start + step, ...`
+pub fn count(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("count", &args, &kwargs, 0..=2, pos)?;
+    let mut values = [0, 1];
+    for (value, arg) in values.iter_mut().zip(&args) {
+        match arg {
+            Object::Int(n) => *value = *n,
+            _ => {
+                return Err(Error::TypeError(
+                    format!("function `count` takes int, not {}", arg.type_name()),
+                    pos,
+                ))
+            }
+        }
+    }
+    Ok(Object::new_iterator(IterState::Count(values[0], values[1])))
+}
+
+// `reduce(xs, f, init)` folds elements from left
+pub fn reduce(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("reduce", &args, &kwargs, 3..=3, pos)?;
+    let iterator = args[0].iter(pos)?;
+    let mut accumulator = args[2].clone();
+    while let Some(element) = iterator.next(pos)? {
+        accumulator = args[1]
+            .clone()
+            .call(vec![accumulator, element], BTreeMap::new(), pos)?;
+    }
+    Ok(accumulator)
+}
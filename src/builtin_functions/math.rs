@@ -18,6 +18,7 @@ pub fn exp(
         match args[0].clone() {
             Object::Int(i) => Ok(Object::Float((i as f64).exp())),
             Object::Float(f) => Ok(Object::Float(f.exp())),
+            Object::Complex(c) => Ok(Object::Complex(c.exp())),
             _ => Err(Error::TypeError(
                 format!("cannot calculate exp({})", args[0].type_name()),
                 pos,
@@ -248,6 +249,7 @@ pub fn ln(
         match args[0].clone() {
             Object::Int(i) => Ok(Object::Float((i as f64).ln())),
             Object::Float(f) => Ok(Object::Float(f.ln())),
+            Object::Complex(c) => Ok(Object::Complex(c.ln())),
             _ => Err(Error::TypeError(
                 format!("cannot calculate ln({})", args[0].type_name()),
                 pos,
@@ -271,6 +273,7 @@ pub fn sqrt(
         match args[0].clone() {
             Object::Int(i) => Ok(Object::Float((i as f64).sqrt())),
             Object::Float(f) => Ok(Object::Float(f.sqrt())),
+            Object::Complex(c) => Ok(Object::Complex(c.sqrt())),
             _ => Err(Error::TypeError(
                 format!("cannot calculate sqrt({})", args[0].type_name()),
                 pos,
@@ -294,6 +297,7 @@ pub fn abs(
         match args[0].clone() {
             Object::Int(i) => Ok(Object::Float((i as f64).abs())),
             Object::Float(f) => Ok(Object::Float(f.abs())),
+            Object::Complex(c) => Ok(Object::Float(c.abs())),
             _ => Err(Error::TypeError(
                 format!("cannot calculate abs({})", args[0].type_name()),
                 pos,
@@ -302,6 +306,81 @@ pub fn abs(
     }
 }
 
+// complex conjugate. real numbers are returned as they are.
+pub fn conj(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    // this function takes only one argument.
+    if !kwargs.is_empty() || args.len() != 1 {
+        Err(Error::ArgumentError(
+            "function `conj` takes only one argument.".to_string(),
+            pos,
+        ))
+    } else {
+        match args[0].clone() {
+            Object::Int(i) => Ok(Object::Int(i)),
+            Object::Float(f) => Ok(Object::Float(f)),
+            Object::Complex(c) => Ok(Object::Complex(c.conj())),
+            _ => Err(Error::TypeError(
+                format!("cannot calculate conj({})", args[0].type_name()),
+                pos,
+            )),
+        }
+    }
+}
+
+// real part. real numbers are returned as they are.
+pub fn re(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    // this function takes only one argument.
+    if !kwargs.is_empty() || args.len() != 1 {
+        Err(Error::ArgumentError(
+            "function `re` takes only one argument.".to_string(),
+            pos,
+        ))
+    } else {
+        match args[0].clone() {
+            Object::Int(i) => Ok(Object::Int(i)),
+            Object::Float(f) => Ok(Object::Float(f)),
+            Object::Complex(c) => Ok(Object::Float(c.re)),
+            _ => Err(Error::TypeError(
+                format!("cannot calculate re({})", args[0].type_name()),
+                pos,
+            )),
+        }
+    }
+}
+
+// imaginary part, which is zero of the same type for real numbers
+pub fn im(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    // this function takes only one argument.
+    if !kwargs.is_empty() || args.len() != 1 {
+        Err(Error::ArgumentError(
+            "function `im` takes only one argument.".to_string(),
+            pos,
+        ))
+    } else {
+        match args[0].clone() {
+            Object::Int(_) => Ok(Object::Int(0)),
+            Object::Float(_) => Ok(Object::Float(0.0)),
+            Object::Complex(c) => Ok(Object::Float(c.im)),
+            _ => Err(Error::TypeError(
+                format!("cannot calculate im({})", args[0].type_name()),
+                pos,
+            )),
+        }
+    }
+}
+
 pub fn modulo(
     args: Vec<Object>,
     kwargs: BTreeMap<String, Object>,
@@ -314,17 +393,6 @@ pub fn modulo(
             pos,
         ))
     } else {
-        if let (Object::Int(a), Object::Int(b)) = (args[0].clone(), args[1].clone()) {
-            Ok(Object::Int(a % b))
-        } else {
-            Err(Error::TypeError(
-                format!(
-                    "cannot calculate mod({}, {})",
-                    args[0].type_name(),
-                    args[1].type_name()
-                ),
-                pos,
-            ))
-        }
+        args[0].clone().modulo(args[1].clone(), pos)
     }
 }
@@ -0,0 +1,454 @@
+use crate::builtin_functions::check_arguments;
+use crate::builtin_functions::format::format_object;
+use crate::core::error::Error;
+use crate::core::method::MethodTable;
+use crate::core::object::{MethodFn, Object};
+use crate::core::parser::Position;
+use crate::core::scheduler::Wait;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+pub fn load_builtin_methods(table: &mut MethodTable) {
+    let mut add = |type_name: &str, name: &str, method: MethodFn| {
+        table.insert((type_name.to_string(), name.to_string()), method);
+    };
+    add("str", "len", len);
+    add("str", "upper", str_upper);
+    add("str", "lower", str_lower);
+    add("str", "trim", str_trim);
+    add("str", "split", str_split);
+    add("str", "replace", str_replace);
+    add("str", "starts_with", str_starts_with);
+    add("str", "ends_with", str_ends_with);
+
+    add("list", "len", len);
+    add("list", "push", list_push);
+    add("list", "pop", list_pop);
+    add("list", "insert", list_insert);
+    add("list", "remove", list_remove);
+    add("list", "join", list_join);
+
+    add("hash", "len", len);
+    add("hash", "keys", hash_keys);
+    add("hash", "values", hash_values);
+    add("hash", "get", hash_get);
+    add("hash", "remove", hash_remove);
+
+    add("tuple", "len", len);
+
+    add("set", "len", len);
+    add("set", "add", set_add);
+    add("set", "remove", set_remove);
+
+    add("channel", "len", len);
+    add("channel", "send", channel_send);
+    add("channel", "recv", channel_recv);
+}
+
+fn receiver_error(receiver: &Object, name: &str, pos: Position) -> Error {
+    Error::TypeError(
+        format!(
+            "method `{}` cannot be used with {}",
+            name,
+            receiver.type_name()
+        ),
+        pos,
+    )
+}
+
+fn expect_str(obj: &Object, name: &str, pos: Position) -> Result<String, Error> {
+    match obj {
+        Object::Str(s) => Ok(s.to_string()),
+        _ => Err(Error::TypeError(
+            format!(
+                "method `{}` requires str argument, given {}",
+                name,
+                obj.type_name()
+            ),
+            pos,
+        )),
+    }
+}
+
+// index for insert/remove. negative index counts from the end.
+fn expect_index(obj: &Object, length: usize, name: &str, pos: Position) -> Result<usize, Error> {
+    match obj {
+        Object::Int(i) => {
+            let index = if *i < 0 { *i + length as i64 } else { *i };
+            if 0 <= index && index as usize <= length {
+                Ok(index as usize)
+            } else {
+                Err(Error::IndexOutOfRange(
+                    format!("index {} is out of range", i),
+                    pos,
+                ))
+            }
+        }
+        _ => Err(Error::TypeError(
+            format!(
+                "method `{}` requires int index, given {}",
+                name,
+                obj.type_name()
+            ),
+            pos,
+        )),
+    }
+}
+
+fn len(
+    receiver: &mut Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("len", &args, &kwargs, 0..=0, pos)?;
+    match receiver {
+        Object::Str(s) => Ok(Object::Int(s.char_count() as i64)),
+        Object::List(l, _) | Object::Tuple(l) => Ok(Object::Int(l.len() as i64)),
+        Object::Set(table, _) | Object::Hash(table, _) => Ok(Object::Int(table.len() as i64)),
+        Object::Channel(queue) => Ok(Object::Int(queue.borrow().len() as i64)),
+        _ => Err(receiver_error(receiver, "len", pos)),
+    }
+}
+
+fn str_upper(
+    receiver: &mut Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("upper", &args, &kwargs, 0..=0, pos)?;
+    match receiver {
+        Object::Str(s) => Ok(Object::Str(s.to_uppercase().into())),
+        _ => Err(receiver_error(receiver, "upper", pos)),
+    }
+}
+
+fn str_lower(
+    receiver: &mut Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("lower", &args, &kwargs, 0..=0, pos)?;
+    match receiver {
+        Object::Str(s) => Ok(Object::Str(s.to_lowercase().into())),
+        _ => Err(receiver_error(receiver, "lower", pos)),
+    }
+}
+
+fn str_trim(
+    receiver: &mut Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("trim", &args, &kwargs, 0..=0, pos)?;
+    match receiver {
+        Object::Str(s) => Ok(Object::Str(s.trim().into())),
+        _ => Err(receiver_error(receiver, "trim", pos)),
+    }
+}
+
+// split by the separator, or by whitespaces if the separator is not given.
+fn str_split(
+    receiver: &mut Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("split", &args, &kwargs, 0..=1, pos)?;
+    let s = match receiver {
+        Object::Str(s) => s,
+        _ => return Err(receiver_error(receiver, "split", pos)),
+    };
+    let parts = match args.first() {
+        Some(separator) => {
+            let separator = expect_str(separator, "split", pos)?;
+            if separator.is_empty() {
+                return Err(Error::ValueError("empty separator".to_string(), pos));
+            }
+            s.split(separator.as_str())
+                .map(|part| Object::Str(part.to_string().into()))
+                .collect()
+        }
+        None => s
+            .split_whitespace()
+            .map(|part| Object::Str(part.to_string().into()))
+            .collect(),
+    };
+    Ok(Object::new_list(parts))
+}
+
+fn str_replace(
+    receiver: &mut Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("replace", &args, &kwargs, 2..=2, pos)?;
+    let from = expect_str(&args[0], "replace", pos)?;
+    let to = expect_str(&args[1], "replace", pos)?;
+    match receiver {
+        Object::Str(s) => Ok(Object::Str(s.replace(from.as_str(), &to).into())),
+        _ => Err(receiver_error(receiver, "replace", pos)),
+    }
+}
+
+fn str_starts_with(
+    receiver: &mut Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("starts_with", &args, &kwargs, 1..=1, pos)?;
+    let prefix = expect_str(&args[0], "starts_with", pos)?;
+    match receiver {
+        Object::Str(s) => Ok(Object::Bool(s.starts_with(prefix.as_str()))),
+        _ => Err(receiver_error(receiver, "starts_with", pos)),
+    }
+}
+
+fn str_ends_with(
+    receiver: &mut Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("ends_with", &args, &kwargs, 1..=1, pos)?;
+    let suffix = expect_str(&args[0], "ends_with", pos)?;
+    match receiver {
+        Object::Str(s) => Ok(Object::Bool(s.ends_with(suffix.as_str()))),
+        _ => Err(receiver_error(receiver, "ends_with", pos)),
+    }
+}
+
+fn list_push(
+    receiver: &mut Object,
+    mut args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("push", &args, &kwargs, 1..=1, pos)?;
+    receiver.check_mutable(pos)?;
+    match receiver {
+        Object::List(l, _) => {
+            Rc::make_mut(l).push(args.remove(0));
+            Ok(Object::Nil)
+        }
+        _ => Err(receiver_error(receiver, "push", pos)),
+    }
+}
+
+fn list_pop(
+    receiver: &mut Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("pop", &args, &kwargs, 0..=0, pos)?;
+    receiver.check_mutable(pos)?;
+    match receiver {
+        Object::List(l, _) => Rc::make_mut(l).pop().ok_or(Error::IndexOutOfRange(
+            "pop from empty list".to_string(),
+            pos,
+        )),
+        _ => Err(receiver_error(receiver, "pop", pos)),
+    }
+}
+
+fn list_insert(
+    receiver: &mut Object,
+    mut args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("insert", &args, &kwargs, 2..=2, pos)?;
+    receiver.check_mutable(pos)?;
+    match receiver {
+        Object::List(l, _) => {
+            let index = expect_index(&args[0], l.len(), "insert", pos)?;
+            Rc::make_mut(l).insert(index, args.remove(1));
+            Ok(Object::Nil)
+        }
+        _ => Err(receiver_error(receiver, "insert", pos)),
+    }
+}
+
+fn list_remove(
+    receiver: &mut Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("remove", &args, &kwargs, 1..=1, pos)?;
+    receiver.check_mutable(pos)?;
+    match receiver {
+        Object::List(l, _) => {
+            let index = expect_index(&args[0], l.len(), "remove", pos)?;
+            if index == l.len() {
+                return Err(Error::IndexOutOfRange(
+                    format!("index {} is out of range", args[0]),
+                    pos,
+                ));
+            }
+            Ok(Rc::make_mut(l).remove(index))
+        }
+        _ => Err(receiver_error(receiver, "remove", pos)),
+    }
+}
+
+// join elements with the separator. str elements are not quoted.
+fn list_join(
+    receiver: &mut Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("join", &args, &kwargs, 0..=1, pos)?;
+    let separator = match args.first() {
+        Some(separator) => expect_str(separator, "join", pos)?,
+        None => "".to_string(),
+    };
+    match receiver {
+        Object::List(l, _) => {
+            let mut elements = vec![];
+            for element in l.iter() {
+                elements.push(format_object(element, "", pos)?);
+            }
+            Ok(Object::Str(elements.join(&separator).into()))
+        }
+        _ => Err(receiver_error(receiver, "join", pos)),
+    }
+}
+
+fn hash_keys(
+    receiver: &mut Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("keys", &args, &kwargs, 0..=0, pos)?;
+    match receiver {
+        Object::Hash(table, _) => Ok(Object::new_list(table.keys().cloned().collect())),
+        _ => Err(receiver_error(receiver, "keys", pos)),
+    }
+}
+
+fn hash_values(
+    receiver: &mut Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("values", &args, &kwargs, 0..=0, pos)?;
+    match receiver {
+        Object::Hash(table, _) => Ok(Object::new_list(table.values().cloned().collect())),
+        _ => Err(receiver_error(receiver, "values", pos)),
+    }
+}
+
+// value of the key, or the default value (nil if not given) if the key is not found.
+fn hash_get(
+    receiver: &mut Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("get", &args, &kwargs, 1..=2, pos)?;
+    match receiver {
+        Object::Hash(table, _) => match table.get(&args[0]) {
+            Some(value) => Ok(value.clone()),
+            None => Ok(args.get(1).cloned().unwrap_or(Object::Nil)),
+        },
+        _ => Err(receiver_error(receiver, "get", pos)),
+    }
+}
+
+fn hash_remove(
+    receiver: &mut Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("remove", &args, &kwargs, 1..=1, pos)?;
+    receiver.check_mutable(pos)?;
+    match receiver {
+        Object::Hash(table, _) => match Rc::make_mut(table).remove(&args[0]) {
+            Some(value) => Ok(value),
+            None => Err(Error::KeyError(
+                format!("key {} is not found", args[0]),
+                pos,
+            )),
+        },
+        _ => Err(receiver_error(receiver, "remove", pos)),
+    }
+}
+
+fn set_add(
+    receiver: &mut Object,
+    mut args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("add", &args, &kwargs, 1..=1, pos)?;
+    receiver.check_mutable(pos)?;
+    match receiver {
+        Object::Set(elements, _) => {
+            args[0].check_hashable(pos)?;
+            Rc::make_mut(elements).insert(args.remove(0), Object::Nil, pos)?;
+            Ok(Object::Nil)
+        }
+        _ => Err(receiver_error(receiver, "add", pos)),
+    }
+}
+
+fn set_remove(
+    receiver: &mut Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("remove", &args, &kwargs, 1..=1, pos)?;
+    receiver.check_mutable(pos)?;
+    match receiver {
+        Object::Set(elements, _) => match Rc::make_mut(elements).remove(&args[0]) {
+            Some(_) => Ok(args[0].clone()),
+            None => Err(Error::KeyError(
+                format!("{} is not in the set", args[0]),
+                pos,
+            )),
+        },
+        _ => Err(receiver_error(receiver, "remove", pos)),
+    }
+}
+
+fn channel_send(
+    receiver: &mut Object,
+    mut args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("send", &args, &kwargs, 1..=1, pos)?;
+    match receiver {
+        Object::Channel(queue) => {
+            queue.borrow_mut().push_back(args.remove(0));
+            Ok(Object::Nil)
+        }
+        _ => Err(receiver_error(receiver, "send", pos)),
+    }
+}
+
+// `await ch.recv()` suspends the task until a value arrives, and gives the value
+fn channel_recv(
+    receiver: &mut Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("recv", &args, &kwargs, 0..=0, pos)?;
+    match receiver {
+        Object::Channel(queue) => Ok(Object::Wait(Wait::Receive(queue.clone()))),
+        _ => Err(receiver_error(receiver, "recv", pos)),
+    }
+}
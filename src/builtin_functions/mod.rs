@@ -1,21 +1,92 @@
 pub mod cast;
+pub mod collection;
+pub mod format;
+pub mod gc;
+pub mod iterator;
 pub mod math;
+pub mod methods;
 pub mod print;
+pub mod task;
 
-use crate::builtin_functions::cast::{get_type, to_float, to_int, to_str};
+use crate::builtin_functions::cast::{get_type, get_variant, to_complex, to_float, to_int, to_str};
+use crate::builtin_functions::collection::{freeze, len, max, min, reverse, sort};
+use crate::builtin_functions::format::format;
+use crate::builtin_functions::gc::gc;
+use crate::builtin_functions::iterator::{
+    count, enumerate, filter, iter, list, map, next, reduce, set, skip, take, tuple, zip,
+};
 use crate::builtin_functions::math::{
-    abs, acos, asin, atan, cos, cosh, exp, ln, modulo, sin, sinh, sqrt, tan, tanh,
+    abs, acos, asin, atan, conj, cos, cosh, exp, im, ln, modulo, re, sin, sinh, sqrt, tan, tanh,
 };
 use crate::builtin_functions::print::{builtin_input, builtin_print};
+use crate::builtin_functions::task::{channel, now, resume, sleep, spawn};
 
 use crate::core::environment::Environment;
+use crate::core::error::Error;
 use crate::core::object::Object;
+use crate::core::parser::Position;
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+// number of positional arguments must be in `count`, and keyword arguments are not allowed.
+// used by built-in functions and methods.
+pub(crate) fn check_arguments(
+    name: &str,
+    args: &[Object],
+    kwargs: &BTreeMap<String, Object>,
+    count: RangeInclusive<usize>,
+    pos: Position,
+) -> Result<(), Error> {
+    if kwargs.is_empty() && count.contains(&args.len()) {
+        return Ok(());
+    }
+    let count = match (count.start(), count.end()) {
+        (min, max) if min == max => format!("{}", min),
+        (min, &usize::MAX) => format!("at least {}", min),
+        (min, max) => format!("{} to {}", min, max),
+    };
+    Err(Error::ArgumentError(
+        format!("`{}` takes {} arguments.", name, count),
+        pos,
+    ))
+}
 
 pub fn load_builtin_functions(env: &mut Environment) {
     env.add_function("str", to_str);
     env.add_function("int", to_int);
     env.add_function("float", to_float);
+    env.add_function("complex", to_complex);
     env.add_function("type", get_type);
+    env.add_function("variant", get_variant);
+    env.add_function("format", format);
+    env.add_function("len", len);
+    env.add_function("sort", sort);
+    env.add_function("reverse", reverse);
+    env.add_function("freeze", freeze);
+    env.add_function("min", min);
+    env.add_function("max", max);
+
+    env.add_function("iter", iter);
+    env.add_function("next", next);
+    env.add_function("list", list);
+    env.add_function("tuple", tuple);
+    env.add_function("set", set);
+    env.add_function("map", map);
+    env.add_function("filter", filter);
+    env.add_function("take", take);
+    env.add_function("skip", skip);
+    env.add_function("enumerate", enumerate);
+    env.add_function("zip", zip);
+    env.add_function("count", count);
+    env.add_function("reduce", reduce);
+
+    env.add_function("spawn", spawn);
+    env.add_function("sleep", sleep);
+    env.add_function("channel", channel);
+    env.add_function("resume", resume);
+    env.add_function("now", now);
+
+    env.add_function("gc", gc);
 
     env.add_function("exp", exp);
     env.add_function("sin", sin);
@@ -31,9 +102,13 @@ pub fn load_builtin_functions(env: &mut Environment) {
     env.add_function("sqrt", sqrt);
     env.add_function("abs", abs);
     env.add_function("mod", modulo);
+    env.add_function("conj", conj);
+    env.add_function("re", re);
+    env.add_function("im", im);
 
     //set pi
-    env.set("pi", Object::Float(std::f64::consts::PI));
+    env.define("pi", Object::Float(std::f64::consts::PI));
+    env.protect("pi");
 }
 
 pub fn load_builtin_stdio(env: &mut Environment) {
@@ -3,8 +3,6 @@ use crate::core::object::Object;
 use crate::core::parser::Position;
 use std::collections::BTreeMap;
 
-use std::io::prelude;
-
 pub fn builtin_print(
     args: Vec<Object>,
     kwargs: BTreeMap<String, Object>,
@@ -17,16 +15,7 @@ pub fn builtin_print(
             pos,
         ))
     } else {
-        let arg = args[0].clone();
-        let mut output = "".to_string();
-        match arg {
-            Object::Str(s) => {
-                output = s;
-            }
-            _ => {
-                output = arg.to_string();
-            }
-        }
+        let output = args[0].to_text(pos)?;
         let mut end = "\n".to_string();
         if kwargs.contains_key("end") {
             let end_obj = kwargs.get("end").unwrap();
@@ -39,8 +28,8 @@ pub fn builtin_print(
                 }
             }
         }
-        print!("{}", format!("{}{}", output, end));
-        Ok(Object::Str(output))
+        print!("{}{}", output, end);
+        Ok(Object::Str(output.into()))
     }
 }
 
@@ -50,7 +39,7 @@ pub fn builtin_input(
     pos: Position,
 ) -> Result<Object, Error> {
     // this function takes only one argument.
-    if !kwargs.is_empty() || args.len() != 0 {
+    if !kwargs.is_empty() || !args.is_empty() {
         Err(Error::ArgumentError(
             "function `input` takes no arguments.".to_string(),
             pos,
@@ -58,6 +47,6 @@ pub fn builtin_input(
     } else {
         let mut buffer = String::new();
         std::io::stdin().read_line(&mut buffer).unwrap();
-        return Ok(Object::Str(buffer));
+        Ok(Object::Str(buffer.into()))
     }
 }
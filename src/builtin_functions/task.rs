@@ -0,0 +1,93 @@
+use crate::builtin_functions::check_arguments;
+use crate::core::error::Error;
+use crate::core::gc::{self, Handle};
+use crate::core::iterator::IterState;
+use crate::core::object::Object;
+use crate::core::parser::Position;
+use crate::core::scheduler::{self, Wait};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::rc::Rc;
+use std::time::Duration;
+
+// `spawn(task())` registers the generator to the scheduler. it runs when the host drives
+// the scheduler.
+pub fn spawn(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("spawn", &args, &kwargs, 1..=1, pos)?;
+    match &args[0] {
+        Object::Iterator(state) if matches!(*state.borrow(), IterState::Generator(_)) => {
+            scheduler::spawn(args[0].clone());
+            Ok(Object::Nil)
+        }
+        _ => Err(Error::TypeError(
+            format!("cannot spawn {}", args[0].type_name()),
+            pos,
+        )),
+    }
+}
+
+// `await sleep(seconds)` suspends the task until the scheduler clock advances
+pub fn sleep(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("sleep", &args, &kwargs, 1..=1, pos)?;
+    let seconds = match args[0] {
+        Object::Int(i) => i as f64,
+        Object::Float(f) => f,
+        _ => {
+            return Err(Error::TypeError(
+                format!("cannot sleep {}", args[0].type_name()),
+                pos,
+            ))
+        }
+    };
+    // negative, NaN, infinite and too long durations are rejected alike
+    if Duration::try_from_secs_f64(seconds).is_err() {
+        return Err(Error::ValueError(
+            format!("cannot sleep {} seconds", seconds),
+            pos,
+        ));
+    }
+    Ok(Object::Wait(Wait::Sleep(seconds)))
+}
+
+pub fn channel(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("channel", &args, &kwargs, 0..=0, pos)?;
+    let queue = Rc::new(RefCell::new(VecDeque::new()));
+    gc::track(Handle::Queue(Rc::downgrade(&queue)));
+    Ok(Object::Channel(queue))
+}
+
+// `resume(g, value)` sends the value to `x = yield ...` of the generator and returns the next
+// yielded value
+pub fn resume(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("resume", &args, &kwargs, 1..=2, pos)?;
+    let sent = args.get(1).cloned().unwrap_or(Object::Nil);
+    args[0]
+        .resume(sent, pos)?
+        .ok_or(Error::ValueError("generator is finished".to_string(), pos))
+}
+
+// current time of the scheduler clock
+pub fn now(
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    check_arguments("now", &args, &kwargs, 0..=0, pos)?;
+    Ok(Object::Float(scheduler::now()))
+}
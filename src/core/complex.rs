@@ -0,0 +1,166 @@
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+// complex number of two f64 parts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Self {
+        Complex { re, im }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.re == 0.0 && self.im == 0.0
+    }
+
+    pub fn conj(self) -> Self {
+        Complex::new(self.re, -self.im)
+    }
+
+    pub fn abs(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn exp(self) -> Self {
+        let r = self.re.exp();
+        Complex::new(r * self.im.cos(), r * self.im.sin())
+    }
+
+    // principal value, whose imaginary part is in (-pi, pi]
+    pub fn ln(self) -> Self {
+        Complex::new(self.abs().ln(), self.arg())
+    }
+
+    // principal square root, whose real part is not negative
+    pub fn sqrt(self) -> Self {
+        if self.is_zero() {
+            return Complex::new(0.0, self.im);
+        }
+        let t = ((self.re.abs() + self.abs()) / 2.0).sqrt();
+        if self.re >= 0.0 {
+            Complex::new(t, self.im / (2.0 * t))
+        } else {
+            Complex::new(self.im.abs() / (2.0 * t), t.copysign(self.im))
+        }
+    }
+
+    // integer powers are calculated by multiplication to keep them exact, e.g. `1j ** 2` is
+    // -1 rather than -1 with a rounding error in the imaginary part. returns `None` for
+    // powers of zero which are not defined.
+    pub fn pow(self, exponent: Complex) -> Option<Self> {
+        if exponent.im == 0.0 && exponent.re.fract() == 0.0 && exponent.re.abs() <= i32::MAX as f64
+        {
+            let n = exponent.re as i32;
+            if n < 0 && self.is_zero() {
+                return None;
+            }
+            let mut result = Complex::new(1.0, 0.0);
+            let mut base = self;
+            let mut n_abs = n.unsigned_abs();
+            while n_abs > 0 {
+                if n_abs & 1 == 1 {
+                    result = result * base;
+                }
+                base = base * base;
+                n_abs >>= 1;
+            }
+            return Some(if n < 0 {
+                Complex::new(1.0, 0.0) / result
+            } else {
+                result
+            });
+        }
+        if self.is_zero() {
+            return if exponent.re > 0.0 {
+                Some(Complex::new(0.0, 0.0))
+            } else {
+                None
+            };
+        }
+        Some((exponent * self.ln()).exp())
+    }
+}
+
+impl From<f64> for Complex {
+    fn from(re: f64) -> Self {
+        Complex::new(re, 0.0)
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+// the divisor is scaled by its larger part to avoid overflow
+impl Div for Complex {
+    type Output = Complex;
+
+    fn div(self, other: Complex) -> Complex {
+        if other.re.abs() >= other.im.abs() {
+            let ratio = other.im / other.re;
+            let denominator = other.re + other.im * ratio;
+            Complex::new(
+                (self.re + self.im * ratio) / denominator,
+                (self.im - self.re * ratio) / denominator,
+            )
+        } else {
+            let ratio = other.re / other.im;
+            let denominator = other.re * ratio + other.im;
+            Complex::new(
+                (self.re * ratio + self.im) / denominator,
+                (self.im * ratio - self.re) / denominator,
+            )
+        }
+    }
+}
+
+impl Neg for Complex {
+    type Output = Complex;
+
+    fn neg(self) -> Complex {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
+// `(1.5+2j)`, or `2j` if the real part is zero
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.im.is_sign_negative() { '-' } else { '+' };
+        if self.re == 0.0 && self.re.is_sign_positive() {
+            write!(f, "{}j", self.im)
+        } else {
+            write!(f, "({}{}{}j)", self.re, sign, self.im.abs())
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use crate::builtin_functions::methods::load_builtin_methods;
+use crate::core::gc::Collector;
+use crate::core::method::MethodTable;
+use crate::core::scheduler::Scheduler;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+// state which belongs to a runtime rather than to a scope. every scope of the runtime shares
+// the same context, and it is activated while the runtime evaluates code, so that built-in
+// functions and methods can reach it.
+pub struct Context {
+    pub methods: RefCell<MethodTable>,
+    pub scheduler: RefCell<Scheduler>,
+    pub collector: RefCell<Collector>,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        let mut methods = MethodTable::new();
+        load_builtin_methods(&mut methods);
+        Context {
+            methods: RefCell::new(methods),
+            scheduler: RefCell::new(Scheduler::default()),
+            collector: RefCell::new(Collector::default()),
+        }
+    }
+}
+
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Context({:p})", self)
+    }
+}
+
+// contexts are compared by identity
+impl PartialEq for Context {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Option<Rc<Context>>> = const { RefCell::new(None) };
+    // used when no runtime is evaluating, e.g. objects made by the host
+    static FALLBACK: Rc<Context> = Rc::new(Context::default());
+}
+
+// context of the runtime which is evaluating now
+pub fn current() -> Rc<Context> {
+    ACTIVE
+        .with(|active| active.borrow().clone())
+        .unwrap_or_else(|| FALLBACK.with(|context| context.clone()))
+}
+
+// restores the previously active context when dropped
+pub struct Activation(Option<Rc<Context>>);
+
+pub fn activate(context: &Rc<Context>) -> Activation {
+    Activation(ACTIVE.with(|active| active.replace(Some(context.clone()))))
+}
+
+impl Drop for Activation {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        ACTIVE.with(|active| *active.borrow_mut() = previous);
+    }
+}
@@ -1,16 +1,82 @@
+use crate::builtin_functions::format::format_object;
 use crate::builtin_functions::load_builtin_functions;
+use crate::core::complex::Complex;
+use crate::core::context::{self, Context};
 use crate::core::error::Error;
-use crate::core::object::Object;
-use crate::core::parser::Node;
+use crate::core::gc::{self, Handle};
+use crate::core::generator::{bind_names, Generator};
+use crate::core::hash_table::HashTable;
+use crate::core::iterator::IterState;
+use crate::core::object::{BuiltInFn, EnumDef, MethodFn, Object, StructDef};
 use crate::core::parser::Position;
+use crate::core::parser::{
+    AssignOperator, CompareOperator, Comprehension, FormatPart, MatchArm, Node, Pattern,
+};
+use crate::core::string::Str;
 use std::cell::RefCell;
-use std::collections::{BTreeMap, HashMap};
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::rc::Rc;
+
+// target of assignment whose index expressions are already evaluated
+enum Place {
+    Variable(String),
+    // `a.b`
+    Attribute(Box<Place>, Object),
+    // `a[i]`
+    Index(Box<Place>, Object),
+    // value which is not bound to any variable, such as `f()` of `f()[0] = 1`
+    Temporary(Object),
+}
+
+// store of variables. it is registered to the garbage collector only when a closure captures
+// it, since otherwise nothing but the running interpreter refers to it.
+fn new_store(variables: BTreeMap<String, Object>) -> Rc<RefCell<BTreeMap<String, Object>>> {
+    Rc::new(RefCell::new(variables))
+}
+
+// element of `a?.b` or `a?[b]`. a missing element is nil instead of an error.
+fn optional_element(
+    receiver: Object,
+    index: Object,
+    methods: bool,
+    pos: Position,
+) -> Result<Object, Error> {
+    let result = if methods {
+        receiver.get_attribute(index, pos)
+    } else {
+        receiver.get_index(index, pos)
+    };
+    match result {
+        Err(Error::KeyError(..))
+        | Err(Error::IndexOutOfRange(..))
+        | Err(Error::AttributeError(..)) => Ok(Object::Nil),
+        result => result,
+    }
+}
+
+// call at tail position which is not performed yet
+struct TailCall {
+    callable: Object,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+}
+
+// result of `evaluate_tail`. the number is how many `return` wrap the result of the call.
+enum Tail {
+    Value(Object),
+    Call(Box<TailCall>, usize),
+}
 
 #[derive(Debug, PartialEq)]
 pub struct Environment {
-    store: Arc<RefCell<BTreeMap<String, Object>>>,
+    store: Rc<RefCell<BTreeMap<String, Object>>>,
     outer: Option<Box<Environment>>,
+    // names which cannot be assigned or dropped, such as built-in functions. inner scopes and
+    // functions inherit them unless they bind the name by themselves, e.g. as a parameter.
+    protected: Rc<HashSet<String>>,
+    // state of the runtime, which is shared by all scopes of it
+    context: Rc<Context>,
 }
 
 impl Clone for Environment {
@@ -18,15 +84,25 @@ impl Clone for Environment {
         Environment {
             store: self.store.clone(),
             outer: self.outer.clone(),
+            protected: self.protected.clone(),
+            context: self.context.clone(),
         }
     }
 }
 
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Environment {
     pub fn new() -> Self {
         let mut env = Environment {
-            store: Arc::new(RefCell::new(BTreeMap::new())),
+            store: new_store(BTreeMap::new()),
             outer: None,
+            protected: Rc::new(HashSet::new()),
+            context: Rc::new(Context::default()),
         };
         load_builtin_functions(&mut env);
         env
@@ -34,14 +110,21 @@ impl Environment {
 
     pub fn detach(mut self) -> Self {
         self.outer = None;
-        self.store = Arc::new(RefCell::new(self.store.clone().borrow().clone()));
+        self.store = new_store(self.store.clone().borrow().clone());
         self
     }
 
+    // whether both environments refer to the same scope
+    pub fn same_scope(&self, other: &Environment) -> bool {
+        Rc::ptr_eq(&self.store, &other.store)
+    }
+
     // enter new scope
     pub fn new_outer(self) -> Self {
         Environment {
-            store: Arc::new(RefCell::new(BTreeMap::new())),
+            store: new_store(BTreeMap::new()),
+            protected: self.protected.clone(),
+            context: self.context.clone(),
             outer: Some(Box::new(self)),
         }
     }
@@ -57,14 +140,24 @@ impl Environment {
         }
     }
 
-    // set object
-    pub fn set(&mut self, name: &str, value: Object) -> Object {
+    // set object. protected variables are not changed, except that the same object can be
+    // written back as the receiver of a method call.
+    pub fn set(&mut self, name: &str, value: Object, pos: Position) -> Result<Object, Error> {
         //println!("SET {:?} TO {} = {}", self, name, value);
+        if self.is_protected(name) {
+            return match self.get(name) {
+                Some(current) if current.is_identical(&value) => Ok(value),
+                _ => Err(Error::ImmutableError(
+                    format!("cannot assign to protected variable `{}`", name),
+                    pos,
+                )),
+            };
+        }
         match &mut self.outer {
             Some(outer) => {
-                if outer.get(&name).is_some() {
+                if outer.get(name).is_some() {
                     //println!("FOUND VALUE {} ON OUTER", name);
-                    outer.set(name, value.clone());
+                    outer.set(name, value.clone(), pos)?;
                     //println!("SET VALUE.");
                 } else {
                     //println!("NOT FOUND VALUE {} ON OUTER", name);
@@ -83,16 +176,65 @@ impl Environment {
             }
         }
         //println!("ENV: {:?}", self);
+        Ok(value)
+    }
+
+    // set object to this scope without looking up outer scopes. the name is not protected in
+    // this scope any more.
+    pub fn define(&mut self, name: &str, value: Object) -> Object {
+        if self.is_protected(name) {
+            Rc::make_mut(&mut self.protected).remove(name);
+        }
+        self.store
+            .borrow_mut()
+            .insert(name.to_string(), value.clone());
         value
     }
 
+    // stores of this scope and outer scopes, which are referenced by closures
+    pub fn trace(&self, out: &mut Vec<Handle>) {
+        out.push(Handle::Store(Rc::downgrade(&self.store)));
+        if let Some(outer) = &self.outer {
+            outer.trace(out);
+        }
+    }
+
+    // register the stores to the garbage collector, since a closure which captures them can
+    // be stored in them. stores which are already registered are skipped.
+    fn track(&self) {
+        let mut stores = vec![];
+        self.trace(&mut stores);
+        stores.into_iter().for_each(gc::track);
+    }
+
+    // move the value out of the variable leaving nil, looking up scopes in the same order as
+    // `set`. containers are modified without copying while their variables do not refer to
+    // them, and the caller writes them back by `set`.
+    pub fn take(&mut self, name: &str) -> Option<Object> {
+        if let Some(outer) = &mut self.outer {
+            if outer.get(name).is_some() {
+                return outer.take(name);
+            }
+        }
+        self.store
+            .borrow_mut()
+            .get_mut(name)
+            .map(|value| std::mem::replace(value, Object::Nil))
+    }
+
     // drop object
-    pub fn drop_value(&mut self, name: &str) {
+    pub fn drop_value(&mut self, name: &str, pos: Position) -> Result<(), Error> {
+        if self.is_protected(name) {
+            return Err(Error::ImmutableError(
+                format!("cannot drop protected variable `{}`", name),
+                pos,
+            ));
+        }
         match &mut self.outer {
             Some(outer) => {
-                if outer.get(&name).is_some() {
+                if outer.get(name).is_some() {
                     //println!("FOUND VALUE {} ON OUTER", name);
-                    outer.drop_value(name);
+                    outer.drop_value(name, pos)?;
                     //println!("DROPPED VALUE.");
                 } else {
                     //println!("NOT FOUND VALUE {} ON OUTER", name);
@@ -106,20 +248,39 @@ impl Environment {
                 //println!("DROPPED VALUE.");
             }
         }
+        Ok(())
     }
 
-    // add built-in(Rust) function
-    pub fn add_function(
-        &mut self,
-        name: &str,
-        function: fn(Vec<Object>, BTreeMap<String, Object>, Position) -> Result<Object, Error>,
-    ) {
-        self.store
+    // protect the variable from being assigned or dropped by scripts
+    pub fn protect(&mut self, name: &str) {
+        Rc::make_mut(&mut self.protected).insert(name.to_string());
+    }
+
+    pub fn is_protected(&self, name: &str) -> bool {
+        self.protected.contains(name)
+    }
+
+    // add built-in(Rust) function, which is protected
+    pub fn add_function(&mut self, name: &str, function: BuiltInFn) {
+        self.define(name, Object::BuiltInFunction(function));
+        self.protect(name);
+    }
+
+    // add or replace built-in(Rust) method of the type. it is visible only to this runtime.
+    pub fn add_method(&mut self, type_name: &str, name: &str, method: MethodFn) {
+        self.context
+            .methods
             .borrow_mut()
-            .insert(name.to_string(), Object::BuiltInFunction(function));
+            .insert((type_name.to_string(), name.to_string()), method);
+    }
+
+    // make the context of this runtime active until the returned value is dropped
+    pub fn activate(&self) -> context::Activation {
+        context::activate(&self.context)
     }
 
     pub fn evaluate_program(&mut self, node: &Node) -> Result<Object, Error> {
+        let _active = self.activate();
         if let Node::Sequence(seq, _pos) = node {
             self.evaluate_sequence(seq, false, false, Position::new(0, 0))
         } else {
@@ -127,15 +288,28 @@ impl Environment {
         }
     }
 
-    fn evaluate_expression(&mut self, node: &Node) -> Result<Object, Error> {
+    pub(crate) fn evaluate_expression(&mut self, node: &Node) -> Result<Object, Error> {
         match node {
             Node::Sequence(seq, pos) => self.evaluate_sequence(seq, false, true, *pos),
             Node::Loop(seq, pos) => self.evaluate_loop(seq, *pos),
+            Node::For(names, iterable, seq, pos) => self.evaluate_for(names, iterable, seq, *pos),
+            Node::Yield(_, _, pos) => Err(Error::ParseError(
+                "`yield` and `await` can be used only as statements of a function".to_string(),
+                *pos,
+            )),
+            Node::Generator(body, _pos) => Ok(Object::new_iterator(IterState::Generator(
+                Generator::new(body.clone(), self.clone()),
+            ))),
             Node::IntegerLiteral(i, pos) => self.evaluate_integer_literal(*i, *pos),
             Node::Bool(b, pos) => self.evaluate_bool_literal(*b, *pos),
             Node::FloatLiteral(f, pos) => self.evaluate_float_literal(*f, *pos),
+            Node::ImaginaryLiteral(f, pos) => self.evaluate_imaginary_literal(*f, *pos),
             Node::StringLiteral(s, pos) => self.evaluate_str_literal(s.clone(), *pos),
+            Node::FormatString(parts, pos) => self.evaluate_format_string(parts, *pos),
             Node::Assign(names, nodes, pos) => self.evaluate_assign(names, nodes, *pos),
+            Node::CompoundAssign(target, operator, value, pos) => {
+                self.evaluate_compound_assign(target, *operator, value, *pos)
+            }
             Node::Identifier(name, pos) => self.evaluate_identifier(name, *pos),
             Node::Function {
                 arguments,
@@ -166,10 +340,33 @@ impl Environment {
             Node::Div(left, right, pos) => self.evaluate_div(left, right, *pos),
             Node::Mul(left, right, pos) => self.evaluate_mul(left, right, *pos),
             Node::Pow(left, right, pos) => self.evaluate_pow(left, right, *pos),
+            Node::FloorDiv(left, right, pos) => self.evaluate_floor_div(left, right, *pos),
+            Node::Mod(left, right, pos) => self.evaluate_mod(left, right, *pos),
             Node::Neg(value, pos) => self.evaluate_neg(value, *pos),
+            Node::BitNot(value, pos) => self.evaluate_bit_not(value, *pos),
+            Node::BitAnd(left, right, pos) => self.evaluate_bit_and(left, right, *pos),
+            Node::BitOr(left, right, pos) => self.evaluate_bit_or(left, right, *pos),
+            Node::BitXor(left, right, pos) => self.evaluate_bit_xor(left, right, *pos),
+            Node::ShiftLeft(left, right, pos) => self.evaluate_shift_left(left, right, *pos),
+            Node::ShiftRight(left, right, pos) => self.evaluate_shift_right(left, right, *pos),
             Node::Return(value, pos) => self.evaluate_return(value, *pos),
             Node::IfElse(cond, a, b, pos) => self.evaluate_ifelse(cond, a, b, *pos),
             Node::CmpEq(left, right, pos) => self.evaluate_cmp_eq(left, right, *pos),
+            Node::CmpNotEq(left, right, pos) => self.evaluate_cmp_not_eq(left, right, *pos),
+            Node::CmpIn(left, right, pos) => self.evaluate_cmp_in(left, right, *pos),
+            Node::CmpNotIn(left, right, pos) => {
+                let b = !self.evaluate_cmp_in(left, right, *pos)?.to_bool();
+                Ok(Object::Bool(b))
+            }
+            Node::CmpIs(left, right, pos) => self.evaluate_cmp_is(left, right, *pos),
+            Node::CmpIsNot(left, right, pos) => {
+                let b = !self.evaluate_cmp_is(left, right, *pos)?.to_bool();
+                Ok(Object::Bool(b))
+            }
+            Node::CmpChain(first, rest, pos) => self.evaluate_cmp_chain(first, rest, *pos),
+            Node::Range(lower, upper, inclusive, pos) => {
+                self.evaluate_range(lower, upper, *inclusive, *pos)
+            }
             Node::CmpLessThan(left, right, pos) => self.evaluate_cmp_less_than(left, right, *pos),
             Node::CmpLessThanEq(left, right, pos) => {
                 self.evaluate_cmp_less_than_eq(left, right, *pos)
@@ -183,9 +380,51 @@ impl Environment {
             Node::LogicalNot(value, pos) => self.evaluate_logical_not(value, *pos),
             Node::LogicalOr(left, right, pos) => self.evaluate_logical_or(left, right, *pos),
             Node::LogicalAnd(left, right, pos) => self.evaluate_logical_and(left, right, *pos),
-            Node::GetAttribute(left, right, pos) => self.evaluate_get_attribute(left, right, *pos),
+            Node::GetAttribute(left, right, pos) => {
+                self.evaluate_get_attribute(left, right, true, *pos)
+            }
+            Node::OptionalGetAttribute(left, right, pos) => {
+                self.evaluate_optional_get_attribute(left, right, true, *pos)
+            }
+            Node::Index(left, right, pos) => self.evaluate_get_attribute(left, right, false, *pos),
+            Node::OptionalIndex(left, right, pos) => {
+                self.evaluate_optional_get_attribute(left, right, false, *pos)
+            }
+            Node::NilCoalesce(left, right, pos) => self.evaluate_nil_coalesce(left, right, *pos),
+            Node::Hash(pairs, pos) => self.evaluate_hash(pairs, *pos),
+            Node::Tuple(nodes, _pos) => Ok(Object::new_tuple(
+                nodes
+                    .iter()
+                    .map(|node| self.evaluate_expression(node))
+                    .collect::<Result<_, _>>()?,
+            )),
+            Node::Set(nodes, pos) => {
+                let elements = nodes
+                    .iter()
+                    .map(|node| self.evaluate_expression(node))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Object::new_set(elements, *pos)
+            }
+            Node::ListComprehension(element, clauses, pos) => {
+                self.evaluate_list_comprehension(element, clauses, *pos)
+            }
+            Node::HashComprehension(key, value, clauses, pos) => {
+                self.evaluate_hash_comprehension(key, value, clauses, *pos)
+            }
+            Node::Match(value, arms, pos) => self.evaluate_match(value, arms, *pos),
+            Node::Struct {
+                name,
+                fields,
+                methods,
+                position,
+            } => self.evaluate_struct(name, fields, methods, *position),
+            Node::Enum {
+                name,
+                variants,
+                methods,
+                position,
+            } => self.evaluate_enum(name, variants, methods, *position),
             Node::Nil(_pos) => Ok(Object::Nil),
-            _ => Ok(Object::Nil),
         }
     }
 
@@ -209,15 +448,37 @@ impl Environment {
         }
     }
 
+    // `a.b` if `methods` is true, otherwise `a[i]` which is never resolved to a method
     fn evaluate_get_attribute(
         &mut self,
         left: &Node,
         right: &Node,
+        methods: bool,
         pos: Position,
     ) -> Result<Object, Error> {
         let left_obj = self.evaluate_expression(left)?;
         let right_obj = self.evaluate_expression(right)?;
-        left_obj.get_attribute(right_obj, pos)
+        if methods {
+            left_obj.get_attribute(right_obj, pos)
+        } else {
+            left_obj.get_index(right_obj, pos)
+        }
+    }
+
+    // nil receiver, missing key and out of range index yield nil
+    fn evaluate_optional_get_attribute(
+        &mut self,
+        left: &Node,
+        right: &Node,
+        methods: bool,
+        pos: Position,
+    ) -> Result<Object, Error> {
+        let left_obj = self.evaluate_expression(left)?;
+        if left_obj == Object::Nil {
+            return Ok(Object::Nil);
+        }
+        let right_obj = self.evaluate_expression(right)?;
+        optional_element(left_obj, right_obj, methods, pos)
     }
 
     fn evaluate_logical_not(&mut self, value: &Node, _pos: Position) -> Result<Object, Error> {
@@ -231,9 +492,13 @@ impl Environment {
         right: &Node,
         _pos: Position,
     ) -> Result<Object, Error> {
-        let b =
-            self.evaluate_expression(left)?.to_bool() || self.evaluate_expression(right)?.to_bool();
-        Ok(Object::Bool(b))
+        // returns the first truthy operand
+        let left_obj = self.evaluate_expression(left)?;
+        if left_obj.to_bool() {
+            Ok(left_obj)
+        } else {
+            self.evaluate_expression(right)
+        }
     }
 
     fn evaluate_logical_and(
@@ -242,21 +507,132 @@ impl Environment {
         right: &Node,
         _pos: Position,
     ) -> Result<Object, Error> {
-        let b =
-            self.evaluate_expression(left)?.to_bool() && self.evaluate_expression(right)?.to_bool();
-        Ok(Object::Bool(b))
+        // returns the first falsy operand
+        let left_obj = self.evaluate_expression(left)?;
+        if left_obj.to_bool() {
+            self.evaluate_expression(right)
+        } else {
+            Ok(left_obj)
+        }
+    }
+
+    fn evaluate_nil_coalesce(
+        &mut self,
+        left: &Node,
+        right: &Node,
+        _pos: Position,
+    ) -> Result<Object, Error> {
+        match self.evaluate_expression(left)? {
+            Object::Nil => self.evaluate_expression(right),
+            left_obj => Ok(left_obj),
+        }
     }
 
     fn evaluate_cmp_eq(
+        &mut self,
+        left: &Node,
+        right: &Node,
+        pos: Position,
+    ) -> Result<Object, Error> {
+        let left = self.evaluate_expression(left)?;
+        let b = left.equals(&self.evaluate_expression(right)?, pos)?;
+        Ok(Object::Bool(b))
+    }
+
+    fn evaluate_cmp_not_eq(
+        &mut self,
+        left: &Node,
+        right: &Node,
+        pos: Position,
+    ) -> Result<Object, Error> {
+        let left = self.evaluate_expression(left)?;
+        let b = !left.equals(&self.evaluate_expression(right)?, pos)?;
+        Ok(Object::Bool(b))
+    }
+
+    fn evaluate_cmp_in(
+        &mut self,
+        left: &Node,
+        right: &Node,
+        pos: Position,
+    ) -> Result<Object, Error> {
+        let item = self.evaluate_expression(left)?;
+        let b = self.evaluate_expression(right)?.contains(&item, pos)?;
+        Ok(Object::Bool(b))
+    }
+
+    fn evaluate_cmp_is(
         &mut self,
         left: &Node,
         right: &Node,
         _pos: Position,
     ) -> Result<Object, Error> {
-        let b = self.evaluate_expression(left)? == self.evaluate_expression(right)?;
+        let b = self
+            .evaluate_expression(left)?
+            .is(&self.evaluate_expression(right)?);
         Ok(Object::Bool(b))
     }
 
+    fn evaluate_range(
+        &mut self,
+        lower: &Node,
+        upper: &Node,
+        inclusive: bool,
+        pos: Position,
+    ) -> Result<Object, Error> {
+        match (
+            self.evaluate_expression(lower)?,
+            self.evaluate_expression(upper)?,
+        ) {
+            (Object::Int(start), Object::Int(end)) if inclusive => match end.checked_add(1) {
+                Some(end) => Ok(Object::Range(start, end)),
+                None => Err(Error::OverflowError(
+                    "range end is too large".to_string(),
+                    pos,
+                )),
+            },
+            (Object::Int(start), Object::Int(end)) => Ok(Object::Range(start, end)),
+            (start, end) => Err(Error::TypeError(
+                format!(
+                    "range bounds must be int, given {} and {}",
+                    start.type_name(),
+                    end.type_name()
+                ),
+                pos,
+            )),
+        }
+    }
+
+    // `a < b < c` is `a < b and b < c`, but `b` is evaluated only once.
+    fn evaluate_cmp_chain(
+        &mut self,
+        first: &Node,
+        rest: &[(CompareOperator, Node)],
+        pos: Position,
+    ) -> Result<Object, Error> {
+        let mut left = self.evaluate_expression(first)?;
+        for (operator, right_node) in rest {
+            let right = self.evaluate_expression(right_node)?;
+            let result = match operator {
+                CompareOperator::Eq => Object::Bool(left.equals(&right, pos)?),
+                CompareOperator::NotEq => Object::Bool(!left.equals(&right, pos)?),
+                CompareOperator::LessThan => left.less_than(right.clone(), pos)?,
+                CompareOperator::LessThanEq => left.less_than_eq(right.clone(), pos)?,
+                CompareOperator::GreaterThan => left.greater_than(right.clone(), pos)?,
+                CompareOperator::GreaterThanEq => left.greater_than_eq(right.clone(), pos)?,
+                CompareOperator::In => Object::Bool(right.contains(&left, pos)?),
+                CompareOperator::NotIn => Object::Bool(!right.contains(&left, pos)?),
+                CompareOperator::Is => Object::Bool(left.is(&right)),
+                CompareOperator::IsNot => Object::Bool(!left.is(&right)),
+            };
+            if !result.to_bool() {
+                return Ok(Object::Bool(false));
+            }
+            left = right;
+        }
+        Ok(Object::Bool(true))
+    }
+
     fn evaluate_cmp_less_than(
         &mut self,
         left: &Node,
@@ -326,12 +702,389 @@ impl Environment {
             .pow(self.evaluate_expression(right)?, pos)
     }
 
+    fn evaluate_floor_div(
+        &mut self,
+        left: &Node,
+        right: &Node,
+        pos: Position,
+    ) -> Result<Object, Error> {
+        self.evaluate_expression(left)?
+            .floor_div(self.evaluate_expression(right)?, pos)
+    }
+
+    fn evaluate_mod(&mut self, left: &Node, right: &Node, pos: Position) -> Result<Object, Error> {
+        self.evaluate_expression(left)?
+            .modulo(self.evaluate_expression(right)?, pos)
+    }
+
+    fn evaluate_bit_not(&mut self, value: &Node, pos: Position) -> Result<Object, Error> {
+        self.evaluate_expression(value)?.bit_not(pos)
+    }
+
+    fn evaluate_bit_and(
+        &mut self,
+        left: &Node,
+        right: &Node,
+        pos: Position,
+    ) -> Result<Object, Error> {
+        self.evaluate_expression(left)?
+            .bit_and(self.evaluate_expression(right)?, pos)
+    }
+
+    fn evaluate_bit_or(
+        &mut self,
+        left: &Node,
+        right: &Node,
+        pos: Position,
+    ) -> Result<Object, Error> {
+        self.evaluate_expression(left)?
+            .bit_or(self.evaluate_expression(right)?, pos)
+    }
+
+    fn evaluate_bit_xor(
+        &mut self,
+        left: &Node,
+        right: &Node,
+        pos: Position,
+    ) -> Result<Object, Error> {
+        self.evaluate_expression(left)?
+            .bit_xor(self.evaluate_expression(right)?, pos)
+    }
+
+    fn evaluate_shift_left(
+        &mut self,
+        left: &Node,
+        right: &Node,
+        pos: Position,
+    ) -> Result<Object, Error> {
+        self.evaluate_expression(left)?
+            .shift_left(self.evaluate_expression(right)?, pos)
+    }
+
+    fn evaluate_shift_right(
+        &mut self,
+        left: &Node,
+        right: &Node,
+        pos: Position,
+    ) -> Result<Object, Error> {
+        self.evaluate_expression(left)?
+            .shift_right(self.evaluate_expression(right)?, pos)
+    }
+
     fn evaluate_list(&mut self, nodes: &[Node], _pos: Position) -> Result<Object, Error> {
         let mut elements = vec![];
         for node in nodes {
             elements.push(self.evaluate_expression(node)?)
         }
-        Ok(Object::List(elements))
+        Ok(Object::new_list(elements))
+    }
+
+    fn evaluate_hash(&mut self, pairs: &[(Node, Node)], pos: Position) -> Result<Object, Error> {
+        let mut table = HashTable::new();
+        for (key_node, value_node) in pairs {
+            let key = self.evaluate_expression(key_node)?;
+            let value = self.evaluate_expression(value_node)?;
+            table.insert(key, value, pos)?;
+        }
+        Ok(Object::Hash(Rc::new(table), false))
+    }
+
+    fn evaluate_list_comprehension(
+        &mut self,
+        element: &Node,
+        clauses: &[Comprehension],
+        pos: Position,
+    ) -> Result<Object, Error> {
+        let mut elements = vec![];
+        let mut env = self.clone().new_outer();
+        env.evaluate_comprehension(clauses, pos, &mut |env| {
+            elements.push(env.evaluate_expression(element)?);
+            Ok(())
+        })?;
+        Ok(Object::new_list(elements))
+    }
+
+    fn evaluate_hash_comprehension(
+        &mut self,
+        key: &Node,
+        value: &Node,
+        clauses: &[Comprehension],
+        pos: Position,
+    ) -> Result<Object, Error> {
+        let mut table = HashTable::new();
+        let mut env = self.clone().new_outer();
+        env.evaluate_comprehension(clauses, pos, &mut |env| {
+            let key = env.evaluate_expression(key)?;
+            let value = env.evaluate_expression(value)?;
+            table.insert(key, value, pos)
+        })?;
+        Ok(Object::Hash(Rc::new(table), false))
+    }
+
+    // run the clauses of a comprehension from left, and call `emit` for each combination
+    // which passes all `if` clauses. variables are bound in the scope of the comprehension.
+    fn evaluate_comprehension(
+        &mut self,
+        clauses: &[Comprehension],
+        pos: Position,
+        emit: &mut dyn FnMut(&mut Environment) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        match clauses.split_first() {
+            None => emit(self),
+            Some((Comprehension::For(names, iterable), rest)) => {
+                let iterator = self.evaluate_expression(iterable)?.iter(pos)?;
+                while let Some(item) = iterator.next(pos)? {
+                    bind_names(self, names, item, pos)?;
+                    self.evaluate_comprehension(rest, pos, emit)?;
+                }
+                Ok(())
+            }
+            Some((Comprehension::If(condition), rest)) => {
+                if self.evaluate_expression(condition)?.to_bool() {
+                    self.evaluate_comprehension(rest, pos, emit)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // define the struct type and bind it to the name
+    fn evaluate_struct(
+        &mut self,
+        name: &str,
+        fields: &[String],
+        methods: &[(String, Node)],
+        pos: Position,
+    ) -> Result<Object, Error> {
+        let mut method_objects = BTreeMap::new();
+        for (method_name, function) in methods {
+            method_objects.insert(method_name.clone(), self.evaluate_expression(function)?);
+        }
+        let def = StructDef {
+            name: name.to_string(),
+            fields: fields.to_vec(),
+            methods: method_objects,
+            enum_name: None,
+        };
+        self.set(name, Object::Struct(Rc::new(def)), pos)
+    }
+
+    // define the enum type and bind it to the name. variants are also bound to their names.
+    fn evaluate_enum(
+        &mut self,
+        name: &str,
+        variants: &[(String, Option<Vec<String>>)],
+        methods: &[(String, Node)],
+        pos: Position,
+    ) -> Result<Object, Error> {
+        let mut method_objects = BTreeMap::new();
+        for (method_name, function) in methods {
+            method_objects.insert(method_name.clone(), self.evaluate_expression(function)?);
+        }
+        let mut variant_objects = vec![];
+        for (variant_name, fields) in variants {
+            let def = Rc::new(StructDef {
+                name: variant_name.clone(),
+                fields: fields.clone().unwrap_or_default(),
+                methods: method_objects.clone(),
+                enum_name: Some(name.to_string()),
+            });
+            let variant = match fields {
+                Some(_) => Object::Struct(def),
+                None => Object::Instance {
+                    def,
+                    fields: Rc::new(RefCell::new(vec![])),
+                },
+            };
+            self.set(variant_name, variant.clone(), pos)?;
+            variant_objects.push((variant_name.clone(), variant));
+        }
+        let def = EnumDef {
+            name: name.to_string(),
+            variants: variant_objects,
+        };
+        self.set(name, Object::Enum(Rc::new(def)), pos)
+    }
+
+    fn evaluate_match(
+        &mut self,
+        value: &Node,
+        arms: &[MatchArm],
+        pos: Position,
+    ) -> Result<Object, Error> {
+        let value = self.evaluate_expression(value)?;
+        let (index, mut env) = self.select_arm(&value, arms, pos)?;
+        let arm = &arms[index];
+        // like if-expression, match does not consume return.
+        match &arm.body {
+            Node::Sequence(nodes, pos) => env.evaluate_sequence(nodes, true, true, *pos),
+            _ => env.evaluate_expression(&arm.body),
+        }
+    }
+
+    // index of the first arm which matches the value, and the scope which has variables bound
+    // by it
+    pub(crate) fn select_arm(
+        &mut self,
+        value: &Object,
+        arms: &[MatchArm],
+        pos: Position,
+    ) -> Result<(usize, Environment), Error> {
+        for (index, arm) in arms.iter().enumerate() {
+            let mut bindings = vec![];
+            if !self.match_pattern(&arm.pattern, value, &mut bindings, pos)? {
+                continue;
+            }
+            let mut env = self.clone().new_outer();
+            for (name, obj) in bindings {
+                env.define(&name, obj);
+            }
+            if let Some(guard) = &arm.guard {
+                if !env.evaluate_expression(guard)?.to_bool() {
+                    continue;
+                }
+            }
+            return Ok((index, env));
+        }
+        Err(Error::MatchError(
+            format!("no pattern matched {}", value),
+            pos,
+        ))
+    }
+
+    // test whether `value` matches `pattern` and collect variables bound by the pattern.
+    fn match_pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: &Object,
+        bindings: &mut Vec<(String, Object)>,
+        pos: Position,
+    ) -> Result<bool, Error> {
+        match pattern {
+            Pattern::Wildcard => Ok(true),
+            Pattern::Literal(node) => Ok(self.evaluate_expression(node)? == *value),
+            Pattern::Range(lower, upper, inclusive) => {
+                let lower = self.evaluate_expression(lower)?;
+                let upper = self.evaluate_expression(upper)?;
+                let above = lower.less_than_eq(value.clone(), pos);
+                let below = if *inclusive {
+                    value.clone().less_than_eq(upper, pos)
+                } else {
+                    value.clone().less_than(upper, pos)
+                };
+                match (above, below) {
+                    (Ok(above), Ok(below)) => Ok(above.to_bool() && below.to_bool()),
+                    _ => Ok(false),
+                }
+            }
+            Pattern::Type(name) => Ok(value.type_name() == name),
+            Pattern::Variant {
+                enum_name,
+                name,
+                fields,
+            } => {
+                let (def, values) = match value {
+                    Object::Instance { def, fields } => (def, fields.borrow().clone()),
+                    _ => return Ok(false),
+                };
+                if def.name != *name || (enum_name.is_some() && def.enum_name != *enum_name) {
+                    return Ok(false);
+                }
+                match fields {
+                    Some(patterns) if patterns.len() != values.len() => Err(Error::MatchError(
+                        format!(
+                            "{} has {} fields, but the pattern has {}",
+                            name,
+                            values.len(),
+                            patterns.len()
+                        ),
+                        pos,
+                    )),
+                    Some(patterns) => {
+                        for (pattern, value) in patterns.iter().zip(values.iter()) {
+                            if !self.match_pattern(pattern, value, bindings, pos)? {
+                                return Ok(false);
+                            }
+                        }
+                        Ok(true)
+                    }
+                    None => Ok(true),
+                }
+            }
+            Pattern::Binding(name, constraint) => {
+                if let Some(constraint) = constraint {
+                    if !self.match_pattern(constraint, value, bindings, pos)? {
+                        return Ok(false);
+                    }
+                }
+                bindings.push((name.clone(), value.clone()));
+                Ok(true)
+            }
+            Pattern::List(patterns) => {
+                let elements = match value {
+                    Object::List(elements, _) => elements,
+                    _ => return Ok(false),
+                };
+                let rest = patterns.iter().position(|p| matches!(p, Pattern::Rest(_)));
+                let (head, tail) = match rest {
+                    Some(i) => (&patterns[..i], &patterns[i + 1..]),
+                    None => (&patterns[..], &patterns[patterns.len()..]),
+                };
+                if rest.is_none() && elements.len() != head.len()
+                    || elements.len() < head.len() + tail.len()
+                {
+                    return Ok(false);
+                }
+                for (p, v) in head.iter().zip(elements.iter()) {
+                    if !self.match_pattern(p, v, bindings, pos)? {
+                        return Ok(false);
+                    }
+                }
+                let tail_begin = elements.len() - tail.len();
+                for (p, v) in tail.iter().zip(elements[tail_begin..].iter()) {
+                    if !self.match_pattern(p, v, bindings, pos)? {
+                        return Ok(false);
+                    }
+                }
+                if let Some(Pattern::Rest(Some(name))) = rest.map(|i| &patterns[i]) {
+                    let middle = elements[head.len()..tail_begin].to_vec();
+                    bindings.push((name.clone(), Object::new_list(middle)));
+                }
+                Ok(true)
+            }
+            Pattern::Rest(_) => Err(Error::MatchError(
+                "`..` can be used only in list pattern".to_string(),
+                pos,
+            )),
+            Pattern::Hash(pairs) => {
+                let table = match value {
+                    Object::Hash(table, _) => table,
+                    _ => return Ok(false),
+                };
+                for (key_node, p) in pairs {
+                    let key = self.evaluate_expression(key_node)?;
+                    match table.get(&key) {
+                        Some(v) => {
+                            if !self.match_pattern(p, v, bindings, pos)? {
+                                return Ok(false);
+                            }
+                        }
+                        None => return Ok(false),
+                    }
+                }
+                Ok(true)
+            }
+            Pattern::Or(patterns) => {
+                for p in patterns {
+                    let mut alternative_bindings = vec![];
+                    if self.match_pattern(p, value, &mut alternative_bindings, pos)? {
+                        bindings.append(&mut alternative_bindings);
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+        }
     }
 
     fn evaluate_return(&mut self, value: &Node, _pos: Position) -> Result<Object, Error> {
@@ -345,69 +1098,218 @@ impl Environment {
         kwarg_nodes: HashMap<String, Node>,
         pos_call: Position,
     ) -> Result<Object, Error> {
+        // method call such as `xs.push(1)` or `xs?.push(1)`. the receiver is written back
+        // after the call.
+        if let Node::GetAttribute(receiver, name, pos)
+        | Node::OptionalGetAttribute(receiver, name, pos) = callable
+        {
+            let place = self.resolve_place(receiver)?;
+            let name = self.evaluate_expression(name)?;
+            let receiver_obj = self.get_place(&place, *pos)?;
+            let callable_obj = match callable {
+                Node::OptionalGetAttribute(..) if receiver_obj == Object::Nil => Object::Nil,
+                Node::OptionalGetAttribute(..) => optional_element(receiver_obj, name, true, *pos)?,
+                _ => receiver_obj.get_attribute(name, *pos)?,
+            };
+            let (args, kwargs) = self.evaluate_arguments(arg_nodes, &kwarg_nodes)?;
+            if let Object::Method { method, .. } = callable_obj {
+                // release the copy of the receiver so that it is not shared during the call
+                drop(callable_obj);
+                let mut receiver = self.take_place(&place, *pos)?;
+                let result = method(&mut receiver, args, kwargs, pos_call);
+                self.set_place(&place, receiver, pos_call)?;
+                return result;
+            }
+            return self.call_object(callable_obj, args, kwargs, pos_call);
+        }
         let callable_obj = self.evaluate_expression(callable)?;
-        match callable_obj {
-            Object::BuiltInFunction(func) => {
-                let mut args_vec = vec![];
-                let mut kwargs_hash = BTreeMap::new();
-                for arg in arg_nodes {
-                    args_vec.push(self.evaluate_expression(arg)?);
+        let (args, kwargs) = self.evaluate_arguments(arg_nodes, &kwarg_nodes)?;
+        self.call_object(callable_obj, args, kwargs, pos_call)
+    }
+
+    fn evaluate_arguments(
+        &mut self,
+        arg_nodes: &[Node],
+        kwarg_nodes: &HashMap<String, Node>,
+    ) -> Result<(Vec<Object>, BTreeMap<String, Object>), Error> {
+        let mut args = vec![];
+        let mut kwargs = BTreeMap::new();
+        for arg in arg_nodes {
+            args.push(self.evaluate_expression(arg)?);
+        }
+        for (key, value) in kwarg_nodes.iter() {
+            kwargs.insert(key.clone(), self.evaluate_expression(value)?);
+        }
+        Ok((args, kwargs))
+    }
+
+    // call the object with evaluated arguments. default values of keyword arguments are
+    // evaluated in the scope where the function is defined, not in the caller's scope.
+    pub fn call_object(
+        &mut self,
+        callable: Object,
+        args: Vec<Object>,
+        kwargs: BTreeMap<String, Object>,
+        pos_call: Position,
+    ) -> Result<Object, Error> {
+        let mut call = TailCall {
+            callable,
+            args,
+            kwargs,
+            pos: pos_call,
+        };
+        // calls at tail position of the body are performed by this loop, so that recursion
+        // in tail position does not grow the stack
+        loop {
+            let TailCall {
+                callable,
+                args,
+                mut kwargs,
+                pos: pos_call,
+            } = call;
+            let (mut env, body) = match callable {
+                Object::Function {
+                    name: _,
+                    args: arg_names,
+                    kwargs: kwarg_nodes,
+                    body,
+                    env,
+                    pos: _,
+                } => {
+                    let mut env = env.clone().detach();
+                    // check number of arguments
+                    if args.len() != arg_names.len() {
+                        return Err(Error::ArgumentError(
+                            format!(
+                                "the function takes {} positional arguments, but {} positional arguments were given.",
+                                arg_names.len(),
+                                args.len()
+                            ),
+                            pos_call,
+                        ));
+                    }
+                    //TODO: detect invalid keyword_arguments ex: f = () -> {}; f(a=1)
+
+                    // defaults are evaluated before any argument is bound, so that they see
+                    // only the scope of the definition
+                    let mut values = vec![];
+                    for (key, default_node) in kwarg_nodes.iter() {
+                        let value = match kwargs.remove(key) {
+                            Some(value) => value,
+                            None => env.evaluate_expression(default_node)?,
+                        };
+                        values.push((key, value));
+                    }
+                    // set arguments
+                    for (key, value) in arg_names.iter().zip(args) {
+                        env.define(key, value);
+                    }
+                    for (key, value) in values {
+                        env.define(key, value);
+                    }
+                    (env, body)
                 }
-                for (key, value) in kwarg_nodes.iter() {
-                    kwargs_hash.insert(key.clone(), self.evaluate_expression(value)?);
+                _ => return callable.call(args, kwargs, pos_call),
+            };
+            // call function
+            match env.evaluate_tail(&body, true)? {
+                Tail::Value(obj) => return Ok(obj.remove_return()),
+                Tail::Call(next, _) => call = *next,
+            }
+        }
+    }
+
+    // evaluate the node like `evaluate_expression`, but calls of functions at tail position
+    // are not performed and returned to `call_object`. a call wrapped by `return` is also
+    // returned with the number of returns, until a block consumes all of them.
+    fn evaluate_tail(&mut self, node: &Node, tail: bool) -> Result<Tail, Error> {
+        let out = match node {
+            Node::CallFunction {
+                callable,
+                arguments,
+                keyword_arguments,
+                position,
+            } if !matches!(
+                **callable,
+                Node::GetAttribute(..) | Node::OptionalGetAttribute(..)
+            ) =>
+            {
+                let callable_obj = self.evaluate_expression(callable)?;
+                let (args, kwargs) = self.evaluate_arguments(arguments, keyword_arguments)?;
+                let call = TailCall {
+                    callable: callable_obj,
+                    args,
+                    kwargs,
+                    pos: *position,
+                };
+                Tail::Call(Box::new(call), 0)
+            }
+            Node::Return(value, _) => match self.evaluate_tail(value, true)? {
+                Tail::Value(obj) => Tail::Value(Object::Return(Box::new(obj))),
+                Tail::Call(call, returns) => Tail::Call(call, returns + 1),
+            },
+            Node::Sequence(nodes, _) => self.evaluate_tail_sequence(nodes, false, tail)?,
+            Node::IfElse(cond, a, b, _) => {
+                let branch = if self.evaluate_expression(cond)?.to_bool() {
+                    a
+                } else {
+                    b
+                };
+                match &**branch {
+                    Node::Sequence(nodes, _) => self.evaluate_tail_sequence(nodes, true, tail)?,
+                    _ => self.evaluate_tail(branch, tail)?,
                 }
-                func(args_vec, kwargs_hash, pos_call)
             }
-            Object::Function {
-                name,
-                args,
-                kwargs,
-                body,
-                mut env,
-                pos: _,
-            } => {
-                let mut env = env.clone().detach();
-                // check number of arguments
-                if arg_nodes.len() != args.len() {
-                    return Err(Error::ArgumentError(
-                        format!(
-                            "the function takes {} positional arguments, but {} positional arguments were given.",
-                            args.len(),
-                            arg_nodes.len()
-                        )
-                        ,
-                        pos_call,
-                    ));
+            Node::Match(value, arms, pos) => {
+                let value = self.evaluate_expression(value)?;
+                let (index, mut env) = self.select_arm(&value, arms, *pos)?;
+                let arm = &arms[index];
+                match &arm.body {
+                    Node::Sequence(nodes, _) => env.evaluate_tail_sequence(nodes, true, tail)?,
+                    _ => env.evaluate_tail(&arm.body, tail)?,
                 }
-                //TODO: detect invalid keyword_arguments ex: f = () -> {}; f(a=1)
+            }
+            _ => Tail::Value(self.evaluate_expression(node)?),
+        };
+        match out {
+            // the call is not at tail position, or all returns are consumed
+            Tail::Call(call, 0) if !tail => {
+                let TailCall {
+                    callable,
+                    args,
+                    kwargs,
+                    pos,
+                } = *call;
+                Ok(Tail::Value(self.call_object(callable, args, kwargs, pos)?))
+            }
+            _ => Ok(out),
+        }
+    }
 
-                // set arguments
-                for (key, value_node) in args.iter().zip(arg_nodes.iter()) {
-                    let r = self.evaluate_expression(value_node)?;
-                    //println!("{}, {:?}", key, r);
-                    env.set(key, r);
+    // `evaluate_sequence` with a new scope for `evaluate_tail`. only the last node is at tail
+    // position.
+    fn evaluate_tail_sequence(
+        &mut self,
+        nodes: &[Node],
+        bypass_return: bool,
+        tail: bool,
+    ) -> Result<Tail, Error> {
+        let mut env = self.clone().new_outer();
+        let mut last = Tail::Value(Object::Nil);
+        for (i, node) in nodes.iter().enumerate() {
+            match env.evaluate_tail(node, tail && i + 1 == nodes.len())? {
+                Tail::Value(Object::Return(obj)) if bypass_return => {
+                    return Ok(Tail::Value(Object::Return(obj)))
                 }
-                for key in kwargs.keys() {
-                    let mut value_node = None;
-                    if let Some(value) = kwarg_nodes.get(key) {
-                        value_node = Some(value)
-                    } else {
-                        value_node = Some(kwargs.get(key).unwrap())
-                    }
-                    let r = self.evaluate_expression(value_node.unwrap())?;
-                    env.set(key, r);
+                Tail::Value(Object::Return(obj)) => return Ok(Tail::Value(*obj)),
+                Tail::Call(call, returns) if returns > 0 => {
+                    let returns = if bypass_return { returns } else { returns - 1 };
+                    return Ok(Tail::Call(call, returns));
                 }
-                // call function
-                Ok(env.evaluate_expression(&body)?.remove_return())
-            }
-            _ => {
-                let c_obj = self.evaluate_expression(callable)?;
-                Err(Error::TypeError(
-                    format!("{} is not callable.", c_obj.type_name()),
-                    pos_call,
-                ))
+                out => last = out,
             }
         }
+        Ok(last)
     }
 
     fn evaluate_function(
@@ -417,11 +1319,12 @@ impl Environment {
         sequence: Node,
         pos: Position,
     ) -> Result<Object, Error> {
+        self.track();
         Ok(Object::Function {
             name: None,
             args: args.to_vec(),
             kwargs,
-            body: sequence,
+            body: Rc::new(sequence),
             pos,
             env: self.clone(),
         })
@@ -473,19 +1376,38 @@ impl Environment {
     }
 
     fn evaluate_loop(&mut self, nodes: &[Node], _pos: Position) -> Result<Object, Error> {
-        let mut last_obj = Object::Nil;
         let mut env = self.clone().new_outer();
         loop {
             for node in nodes {
                 let out = env.evaluate_expression(node)?;
-                match out {
-                    Object::Return(obj) => return Ok(*obj),
-                    _ => {}
+                if let Object::Return(obj) = out {
+                    return Ok(*obj);
                 }
             }
         }
     }
 
+    fn evaluate_for(
+        &mut self,
+        names: &[String],
+        iterable: &Node,
+        nodes: &[Node],
+        pos: Position,
+    ) -> Result<Object, Error> {
+        let iterator = self.evaluate_expression(iterable)?.iter(pos)?;
+        let mut env = self.clone().new_outer();
+        while let Some(item) = iterator.next(pos)? {
+            bind_names(&mut env, names, item, pos)?;
+            for node in nodes {
+                let out = env.evaluate_expression(node)?;
+                if let Object::Return(obj) = out {
+                    return Ok(*obj);
+                }
+            }
+        }
+        Ok(Object::Nil)
+    }
+
     fn evaluate_bool_literal(&mut self, b: bool, _pos: Position) -> Result<Object, Error> {
         Ok(Object::Bool(b))
     }
@@ -498,10 +1420,32 @@ impl Environment {
         Ok(Object::Float(f))
     }
 
-    fn evaluate_str_literal(&mut self, s: String, _pos: Position) -> Result<Object, Error> {
+    fn evaluate_imaginary_literal(&mut self, f: f64, _pos: Position) -> Result<Object, Error> {
+        Ok(Object::Complex(Complex::new(0.0, f)))
+    }
+
+    fn evaluate_str_literal(&mut self, s: Str, _pos: Position) -> Result<Object, Error> {
         Ok(Object::Str(s))
     }
 
+    fn evaluate_format_string(
+        &mut self,
+        parts: &[FormatPart],
+        pos: Position,
+    ) -> Result<Object, Error> {
+        let mut output = String::new();
+        for part in parts {
+            match part {
+                FormatPart::Literal(s) => output.push_str(s),
+                FormatPart::Expression(node, spec) => {
+                    let obj = self.evaluate_expression(node)?;
+                    output.push_str(&format_object(&obj, spec, pos)?);
+                }
+            }
+        }
+        Ok(Object::Str(output.into()))
+    }
+
     fn evaluate_assign(
         &mut self,
         lefts: &[Node],
@@ -512,31 +1456,157 @@ impl Environment {
             let r = self.evaluate_expression(&rights[0])?;
             match &lefts[0] {
                 Node::Identifier(name, _n_pos) => {
-                    self.set(name, r.clone());
+                    self.set(name, r.clone(), pos)?;
                 }
-                _ => {
-                    todo!()
+                target => {
+                    let place = self.resolve_place(target)?;
+                    self.set_place(&place, r.clone(), pos)?;
                 }
             }
             Ok(r)
         } else {
             let mut output = vec![];
             for (l, r) in lefts.iter().zip(rights.iter()) {
-                output.push(self.evaluate_assign(&[l.clone()], &[r.clone()], pos)?)
+                output.push(self.evaluate_assign(
+                    std::slice::from_ref(l),
+                    std::slice::from_ref(r),
+                    pos,
+                )?)
+            }
+            Ok(Object::new_list(output))
+        }
+    }
+
+    fn evaluate_compound_assign(
+        &mut self,
+        target: &Node,
+        operator: AssignOperator,
+        value: &Node,
+        pos: Position,
+    ) -> Result<Object, Error> {
+        // index expressions of the target are evaluated only once.
+        let place = self.resolve_place(target)?;
+        let current = self.get_place(&place, pos)?;
+        let value = self.evaluate_expression(value)?;
+        let result = match operator {
+            AssignOperator::Add => current.add(value, pos)?,
+            AssignOperator::Sub => current.sub(value, pos)?,
+            AssignOperator::Mul => current.mul(value, pos)?,
+            AssignOperator::Div => current.div(value, pos)?,
+            AssignOperator::Pow => current.pow(value, pos)?,
+            AssignOperator::Mod => current.modulo(value, pos)?,
+            AssignOperator::FloorDiv => current.floor_div(value, pos)?,
+            AssignOperator::BitAnd => current.bit_and(value, pos)?,
+            AssignOperator::BitOr => current.bit_or(value, pos)?,
+            AssignOperator::BitXor => current.bit_xor(value, pos)?,
+            AssignOperator::ShiftLeft => current.shift_left(value, pos)?,
+            AssignOperator::ShiftRight => current.shift_right(value, pos)?,
+        };
+        self.set_place(&place, result.clone(), pos)?;
+        Ok(result)
+    }
+
+    fn resolve_place(&mut self, target: &Node) -> Result<Place, Error> {
+        match target {
+            Node::Identifier(name, _pos) => Ok(Place::Variable(name.clone())),
+            Node::GetAttribute(receiver, name, _pos) => {
+                let receiver = self.resolve_place(receiver)?;
+                let name = self.evaluate_expression(name)?;
+                Ok(Place::Attribute(Box::new(receiver), name))
+            }
+            Node::Index(receiver, index, _pos) => {
+                let receiver = self.resolve_place(receiver)?;
+                let index = self.evaluate_expression(index)?;
+                Ok(Place::Index(Box::new(receiver), index))
+            }
+            Node::OptionalGetAttribute(receiver, index, pos)
+            | Node::OptionalIndex(receiver, index, pos) => {
+                let methods = matches!(target, Node::OptionalGetAttribute(..));
+                let receiver = self.resolve_place(receiver)?;
+                let receiver_obj = self.get_place(&receiver, *pos)?;
+                if receiver_obj == Object::Nil {
+                    return Ok(Place::Temporary(Object::Nil));
+                }
+                let index = self.evaluate_expression(index)?;
+                // a missing element ends the chain with nil, which is not written back
+                match optional_element(receiver_obj, index.clone(), methods, *pos)? {
+                    Object::Nil => Ok(Place::Temporary(Object::Nil)),
+                    _ if methods => Ok(Place::Attribute(Box::new(receiver), index)),
+                    _ => Ok(Place::Index(Box::new(receiver), index)),
+                }
+            }
+            _ => Ok(Place::Temporary(self.evaluate_expression(target)?)),
+        }
+    }
+
+    fn get_place(&self, place: &Place, pos: Position) -> Result<Object, Error> {
+        match place {
+            Place::Variable(name) => self
+                .get(name)
+                .ok_or(Error::VariableNotInitialized(name.clone(), pos)),
+            Place::Attribute(receiver, name) => self
+                .get_place(receiver, pos)?
+                .get_attribute(name.clone(), pos),
+            Place::Index(receiver, index) => {
+                self.get_place(receiver, pos)?.get_index(index.clone(), pos)
+            }
+            Place::Temporary(obj) => Ok(obj.clone()),
+        }
+    }
+
+    // value of the place. lists, hashes and sets are moved out of the place leaving nil, so
+    // that they are not shared and can be modified in place. they must be written back by
+    // `set_place`.
+    fn take_place(&mut self, place: &Place, pos: Position) -> Result<Object, Error> {
+        match place {
+            Place::Variable(name) => match self.get(name) {
+                Some(Object::List(_, _) | Object::Hash(_, _) | Object::Set(_, _))
+                    if !self.is_protected(name) =>
+                {
+                    Ok(self.take(name).unwrap_or(Object::Nil))
+                }
+                Some(value) => Ok(value),
+                None => Err(Error::VariableNotInitialized(name.clone(), pos)),
+            },
+            Place::Attribute(receiver, index) | Place::Index(receiver, index) => {
+                let mut container = self.take_place(receiver, pos)?;
+                let element = container.take_element(index);
+                self.set_place(receiver, container, pos)?;
+                match element {
+                    Some(element) => Ok(element),
+                    None => self.get_place(place, pos),
+                }
+            }
+            Place::Temporary(obj) => Ok(obj.clone()),
+        }
+    }
+
+    fn set_place(&mut self, place: &Place, value: Object, pos: Position) -> Result<(), Error> {
+        match place {
+            Place::Variable(name) => {
+                self.set(name, value, pos)?;
+                Ok(())
+            }
+            Place::Attribute(receiver, index) | Place::Index(receiver, index) => {
+                let mut container = self.take_place(receiver, pos)?;
+                let result = container.set_attribute(index.clone(), value, pos);
+                // the container is written back even on error since it was moved out
+                self.set_place(receiver, container, pos)?;
+                result
             }
-            Ok(Object::List(output))
+            Place::Temporary(_) => Ok(()),
         }
     }
 
-    fn evaluate_identifier(&mut self, name: &String, pos: Position) -> Result<Object, Error> {
+    fn evaluate_identifier(&mut self, name: &str, pos: Position) -> Result<Object, Error> {
         self.get(name)
-            .ok_or(Error::VariableNotInitialized(name.clone(), pos))
+            .ok_or(Error::VariableNotInitialized(name.to_string(), pos))
     }
 
     fn evaluate_drop(&mut self, names: &[String], pos: Position) -> Result<Object, Error> {
         for name in names {
             if self.get(name).is_some() {
-                self.drop_value(name);
+                self.drop_value(name, pos)?;
             } else {
                 return Err(Error::VariableNotInitialized(name.clone(), pos));
             }
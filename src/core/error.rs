@@ -9,12 +9,15 @@ pub enum Error {
     IndexOutOfRange(String, Position),
     ParseError(String, Position),
     InstructionLimit(String, Position),
+    KeyError(String, Position),
+    MatchError(String, Position),
+    ValueError(String, Position),
+    OverflowError(String, Position),
+    AttributeError(String, Position),
+    ImmutableError(String, Position),
 }
 
 pub fn generate_error_message(error: Error, code: &str) -> String {
-    let mut output = "".to_string();
     let _code = code.to_string();
-
-    output = format!("{:?}", error);
-    output
+    format!("{:?}", error)
 }
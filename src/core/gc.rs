@@ -0,0 +1,277 @@
+use crate::core::context;
+use crate::core::hash_table::HashTable;
+use crate::core::iterator::IterState;
+use crate::core::object::{EnumDef, Object, StructDef};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::rc::Weak;
+
+// objects are reference counted, and cycles among them are found by trial deletion: an object
+// is alive if it has more references than those from other interpreter objects (such as from
+// the host or the running interpreter), or it is reachable from such an object. only mutable
+// objects can close a cycle, so they are registered as the start points: instances, iterators
+// and channels on creation, and scopes when a closure captures them.
+
+// collection runs automatically when this number of objects are created since the last one
+const MIN_THRESHOLD: usize = 10000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GcStats {
+    // number of collections
+    pub collections: usize,
+    // total number of objects reclaimed by collections
+    pub collected: usize,
+    // number of objects registered after the last collection
+    pub tracked: usize,
+}
+
+// reference to an object which can be a part of a cycle
+#[derive(Clone)]
+pub enum Handle {
+    Store(Weak<RefCell<BTreeMap<String, Object>>>),
+    Fields(Weak<RefCell<Vec<Object>>>),
+    Iterator(Weak<RefCell<IterState>>),
+    Queue(Weak<RefCell<VecDeque<Object>>>),
+    // immutable objects are not registered, but cycles can pass through them
+    Sequence(Weak<Vec<Object>>),
+    Table(Weak<HashTable>),
+    Struct(Weak<StructDef>),
+    Enum(Weak<EnumDef>),
+}
+
+impl Handle {
+    fn address(&self) -> usize {
+        match self {
+            Handle::Store(w) => w.as_ptr() as *const () as usize,
+            Handle::Fields(w) => w.as_ptr() as *const () as usize,
+            Handle::Iterator(w) => w.as_ptr() as *const () as usize,
+            Handle::Queue(w) => w.as_ptr() as *const () as usize,
+            Handle::Sequence(w) => w.as_ptr() as *const () as usize,
+            Handle::Table(w) => w.as_ptr() as *const () as usize,
+            Handle::Struct(w) => w.as_ptr() as *const () as usize,
+            Handle::Enum(w) => w.as_ptr() as *const () as usize,
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Handle::Store(w) => w.strong_count(),
+            Handle::Fields(w) => w.strong_count(),
+            Handle::Iterator(w) => w.strong_count(),
+            Handle::Queue(w) => w.strong_count(),
+            Handle::Sequence(w) => w.strong_count(),
+            Handle::Table(w) => w.strong_count(),
+            Handle::Struct(w) => w.strong_count(),
+            Handle::Enum(w) => w.strong_count(),
+        }
+    }
+
+    fn is_mutable(&self) -> bool {
+        matches!(
+            self,
+            Handle::Store(_) | Handle::Fields(_) | Handle::Iterator(_) | Handle::Queue(_)
+        )
+    }
+
+    // references held by the object. an object which is borrowed now is in use by the
+    // interpreter, and its references are not counted, which keeps them alive.
+    fn trace(&self, out: &mut Vec<Handle>) {
+        match self {
+            Handle::Store(w) => {
+                if let Some(store) = w.upgrade() {
+                    if let Ok(store) = store.try_borrow() {
+                        store.values().for_each(|value| value.trace(out));
+                    }
+                }
+            }
+            Handle::Fields(w) => {
+                if let Some(fields) = w.upgrade() {
+                    if let Ok(fields) = fields.try_borrow() {
+                        fields.iter().for_each(|value| value.trace(out));
+                    }
+                }
+            }
+            Handle::Iterator(w) => {
+                if let Some(state) = w.upgrade() {
+                    if let Ok(state) = state.try_borrow() {
+                        state.trace(out);
+                    }
+                }
+            }
+            Handle::Queue(w) => {
+                if let Some(queue) = w.upgrade() {
+                    if let Ok(queue) = queue.try_borrow() {
+                        queue.iter().for_each(|value| value.trace(out));
+                    }
+                }
+            }
+            Handle::Sequence(w) => {
+                if let Some(elements) = w.upgrade() {
+                    elements.iter().for_each(|value| value.trace(out));
+                }
+            }
+            Handle::Table(w) => {
+                if let Some(table) = w.upgrade() {
+                    for (key, value) in table.iter() {
+                        key.trace(out);
+                        value.trace(out);
+                    }
+                }
+            }
+            Handle::Struct(w) => {
+                if let Some(def) = w.upgrade() {
+                    def.methods.values().for_each(|method| method.trace(out));
+                }
+            }
+            Handle::Enum(w) => {
+                if let Some(def) = w.upgrade() {
+                    def.variants
+                        .iter()
+                        .for_each(|(_, variant)| variant.trace(out));
+                }
+            }
+        }
+    }
+
+    // drop the contents of unreachable object to break the cycle. the contents are dropped
+    // after the borrow is released.
+    fn clear(&self) {
+        match self {
+            Handle::Store(w) => {
+                if let Some(store) = w.upgrade() {
+                    let contents = store.try_borrow_mut().map(|mut s| std::mem::take(&mut *s));
+                    drop(contents);
+                }
+            }
+            Handle::Fields(w) => {
+                if let Some(fields) = w.upgrade() {
+                    let contents = fields.try_borrow_mut().map(|mut f| std::mem::take(&mut *f));
+                    drop(contents);
+                }
+            }
+            Handle::Iterator(w) => {
+                if let Some(state) = w.upgrade() {
+                    let contents = state
+                        .try_borrow_mut()
+                        .map(|mut s| std::mem::replace(&mut *s, IterState::Range(0, 0)));
+                    drop(contents);
+                }
+            }
+            Handle::Queue(w) => {
+                if let Some(queue) = w.upgrade() {
+                    let contents = queue.try_borrow_mut().map(|mut q| std::mem::take(&mut *q));
+                    drop(contents);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// objects registered by a runtime. each runtime has its own collector in its context.
+pub struct Collector {
+    // keyed by address, which is not reused while the handle is kept
+    objects: HashMap<usize, Handle>,
+    // number of objects registered since the last collection
+    allocated: usize,
+    threshold: usize,
+    stats: GcStats,
+}
+
+impl Default for Collector {
+    fn default() -> Self {
+        Collector {
+            objects: HashMap::new(),
+            allocated: 0,
+            threshold: MIN_THRESHOLD,
+            stats: GcStats::default(),
+        }
+    }
+}
+
+// the collector of the active runtime
+fn with_collector<T>(f: impl FnOnce(&mut Collector) -> T) -> T {
+    f(&mut context::current().collector.borrow_mut())
+}
+
+// register a mutable object, and collect garbage if many objects are registered
+pub fn track(handle: Handle) {
+    let full = with_collector(|c| {
+        if c.objects.insert(handle.address(), handle).is_some() {
+            return false;
+        }
+        c.allocated += 1;
+        c.allocated >= c.threshold
+    });
+    if full {
+        collect();
+    }
+}
+
+// reclaim objects which are unreachable but kept alive by cycles, and returns the number of
+// them
+pub fn collect() -> usize {
+    let objects = with_collector(|c| {
+        c.objects.retain(|_, handle| handle.strong_count() > 0);
+        c.objects.values().cloned().collect::<Vec<Handle>>()
+    });
+
+    // count references among the objects reachable from the registered ones
+    let mut nodes: HashMap<usize, (Handle, usize)> = HashMap::new();
+    let mut pending = vec![];
+    for handle in objects {
+        nodes.entry(handle.address()).or_insert_with(|| {
+            pending.push(handle.clone());
+            (handle, 0)
+        });
+    }
+    let mut children = vec![];
+    while let Some(handle) = pending.pop() {
+        handle.trace(&mut children);
+        for child in children.drain(..) {
+            nodes
+                .entry(child.address())
+                .or_insert_with(|| {
+                    pending.push(child.clone());
+                    (child, 0)
+                })
+                .1 += 1;
+        }
+    }
+
+    // objects referenced from outside are alive, and so are objects reachable from them
+    let mut alive = HashSet::new();
+    let mut pending: Vec<Handle> = nodes
+        .values()
+        .filter(|(handle, internal)| handle.strong_count() > *internal)
+        .map(|(handle, _)| handle.clone())
+        .collect();
+    while let Some(handle) = pending.pop() {
+        if !alive.insert(handle.address()) {
+            continue;
+        }
+        handle.trace(&mut children);
+        pending.append(&mut children);
+    }
+
+    let garbage: Vec<Handle> = nodes
+        .into_values()
+        .map(|(handle, _)| handle)
+        .filter(|handle| handle.is_mutable() && !alive.contains(&handle.address()))
+        .collect();
+    garbage.iter().for_each(Handle::clear);
+
+    with_collector(|c| {
+        c.objects.retain(|_, handle| handle.strong_count() > 0);
+        c.allocated = 0;
+        c.threshold = c.objects.len().max(MIN_THRESHOLD);
+        c.stats.collections += 1;
+        c.stats.collected += garbage.len();
+        c.stats.tracked = c.objects.len();
+    });
+    garbage.len()
+}
+
+pub fn stats() -> GcStats {
+    with_collector(|c| c.stats)
+}
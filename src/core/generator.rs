@@ -0,0 +1,309 @@
+use crate::core::environment::Environment;
+use crate::core::error::Error;
+use crate::core::gc::Handle;
+use crate::core::object::Object;
+use crate::core::parser::{contains_yield, Node, Position};
+use std::rc::Rc;
+
+// generator is a function body which can be suspended at `yield`. statements which contain
+// `yield` (blocks, loops, if and match) are executed on an explicit stack so that they can be
+// resumed, and other statements are evaluated as usual. frames refer to their statements by
+// the path from the body, so that steps do not copy the syntax tree.
+#[derive(Debug)]
+pub struct Generator {
+    body: Rc<Node>,
+    stack: Vec<Frame>,
+    // variable of `name = yield value` which receives the value sent on resume
+    pending: Option<(String, Environment)>,
+}
+
+// step from a node to its child
+#[derive(Debug, Clone, Copy)]
+enum Edge {
+    Statement(usize),
+    Then,
+    Else,
+    Arm(usize),
+}
+
+#[derive(Debug)]
+struct Frame {
+    kind: Kind,
+    // node whose statements are executed
+    path: Vec<Edge>,
+    // the node itself is the only statement, e.g. a branch of if which is not a block
+    single: bool,
+    index: usize,
+    env: Environment,
+}
+
+#[derive(Debug)]
+enum Kind {
+    // branches of if and match do not consume return
+    Block { consume_return: bool, last: Object },
+    Loop,
+    // `index == statements.len()` means the next element should be taken from the iterator
+    For { iterator: Object },
+}
+
+// result of executing a statement. the path of a pushed frame is relative to the statement.
+enum Step {
+    Value(Object),
+    Push(Frame),
+    Yield(Option<String>, Object),
+}
+
+impl Generator {
+    pub fn new(body: Rc<Node>, env: Environment) -> Self {
+        Generator {
+            body,
+            stack: vec![Frame {
+                kind: Kind::Block {
+                    consume_return: true,
+                    last: Object::Nil,
+                },
+                path: vec![],
+                single: true,
+                index: 0,
+                env,
+            }],
+            pending: None,
+        }
+    }
+
+    pub fn trace(&self, out: &mut Vec<Handle>) {
+        for frame in &self.stack {
+            frame.env.trace(out);
+            match &frame.kind {
+                Kind::Block { last, .. } => last.trace(out),
+                Kind::Loop => {}
+                Kind::For { iterator } => iterator.trace(out),
+            }
+        }
+        if let Some((_, env)) = &self.pending {
+            env.trace(out);
+        }
+    }
+
+    // send the value to the suspended `yield` and run until the next `yield`. `None` means the
+    // generator is finished.
+    pub fn resume(&mut self, sent: Object, pos: Position) -> Result<Option<Object>, Error> {
+        if let Some((name, mut env)) = self.pending.take() {
+            env.set(&name, sent, pos)?;
+        }
+        let result = self.run(pos);
+        if !matches!(result, Ok(Some(_))) {
+            // finished or failed generator cannot be resumed
+            self.stack.clear();
+        }
+        result
+    }
+
+    fn run(&mut self, pos: Position) -> Result<Option<Object>, Error> {
+        let body = self.body.clone();
+        loop {
+            let frame = match self.stack.last_mut() {
+                None => return Ok(None),
+                Some(frame) => frame,
+            };
+            let node = locate(&body, &frame.path);
+            let statements = if frame.single {
+                std::slice::from_ref(node)
+            } else {
+                statements(node)
+            };
+            if frame.index >= statements.len() {
+                match &mut frame.kind {
+                    Kind::Block { last, .. } => {
+                        let last = std::mem::replace(last, Object::Nil);
+                        self.stack.pop();
+                        self.deliver(last);
+                        continue;
+                    }
+                    Kind::Loop if statements.is_empty() => {
+                        return Err(Error::ValueError(
+                            "empty loop in generator never yields".to_string(),
+                            pos,
+                        ));
+                    }
+                    Kind::Loop => frame.index = 0,
+                    Kind::For { iterator } => {
+                        match iterator.next(pos)? {
+                            Some(item) => {
+                                if let Node::For(names, ..) = node {
+                                    bind_names(&mut frame.env, names, item, pos)?;
+                                }
+                                frame.index = 0;
+                            }
+                            None => {
+                                self.stack.pop();
+                                self.deliver(Object::Nil);
+                            }
+                        }
+                        continue;
+                    }
+                }
+            }
+            let index = frame.index;
+            frame.index += 1;
+            let mut env = frame.env.clone();
+            match execute(&statements[index], &mut env, pos)? {
+                Step::Value(value) => self.deliver(value),
+                Step::Push(mut child) => {
+                    let parent = &self.stack[self.stack.len() - 1];
+                    let mut path = parent.path.clone();
+                    if !parent.single {
+                        path.push(Edge::Statement(index));
+                    }
+                    path.append(&mut child.path);
+                    child.path = path;
+                    self.stack.push(child);
+                }
+                // the value of `yield` statement is nil
+                Step::Yield(target, value) => {
+                    self.pending = target.map(|name| (name, env));
+                    self.deliver(Object::Nil);
+                    return Ok(Some(value));
+                }
+            }
+        }
+    }
+
+    // pass the value of a finished statement to the frame which executed it
+    fn deliver(&mut self, mut value: Object) {
+        while let Some(frame) = self.stack.last_mut() {
+            match (&mut frame.kind, value) {
+                (
+                    Kind::Block {
+                        consume_return: false,
+                        ..
+                    },
+                    Object::Return(obj),
+                ) => {
+                    self.stack.pop();
+                    value = Object::Return(obj);
+                }
+                (_, Object::Return(obj)) => {
+                    self.stack.pop();
+                    value = *obj;
+                }
+                (Kind::Block { last, .. }, obj) => {
+                    *last = obj;
+                    return;
+                }
+                _ => return,
+            }
+        }
+    }
+}
+
+fn locate<'a>(body: &'a Node, path: &[Edge]) -> &'a Node {
+    path.iter().fold(body, |node, edge| match (edge, node) {
+        (Edge::Statement(i), _) => &statements(node)[*i],
+        (Edge::Then, Node::IfElse(_, a, _, _)) => a,
+        (Edge::Else, Node::IfElse(_, _, b, _)) => b,
+        (Edge::Arm(i), Node::Match(_, arms, _)) => &arms[*i].body,
+        _ => unreachable!(),
+    })
+}
+
+// statements of a block or loop
+fn statements(node: &Node) -> &[Node] {
+    match node {
+        Node::Sequence(nodes, _) | Node::Loop(nodes, _) | Node::For(_, _, nodes, _) => nodes,
+        _ => std::slice::from_ref(node),
+    }
+}
+
+fn execute(node: &Node, env: &mut Environment, pos: Position) -> Result<Step, Error> {
+    if !contains_yield(node) {
+        return Ok(Step::Value(env.evaluate_expression(node)?));
+    }
+    // frame for the statements of the node itself in a new scope
+    let frame = |kind, index, env: &Environment| Frame {
+        kind,
+        path: vec![],
+        single: false,
+        index,
+        env: env.clone().new_outer(),
+    };
+    match node {
+        Node::Yield(target, value, _) => {
+            Ok(Step::Yield(target.clone(), env.evaluate_expression(value)?))
+        }
+        Node::Sequence(_, _) => Ok(Step::Push(frame(
+            Kind::Block {
+                consume_return: true,
+                last: Object::Nil,
+            },
+            0,
+            env,
+        ))),
+        Node::Loop(_, _) => Ok(Step::Push(frame(Kind::Loop, 0, env))),
+        Node::For(_, iterable, nodes, pos) => {
+            let iterator = env.evaluate_expression(iterable)?.iter(*pos)?;
+            Ok(Step::Push(frame(Kind::For { iterator }, nodes.len(), env)))
+        }
+        Node::IfElse(condition, a, b, _) => {
+            let (branch, edge) = if env.evaluate_expression(condition)?.to_bool() {
+                (a, Edge::Then)
+            } else {
+                (b, Edge::Else)
+            };
+            Ok(Step::Push(branch_frame(branch, edge, env.clone())))
+        }
+        Node::Match(value, arms, pos) => {
+            let value = env.evaluate_expression(value)?;
+            let (index, arm_env) = env.select_arm(&value, arms, *pos)?;
+            Ok(Step::Push(branch_frame(
+                &arms[index].body,
+                Edge::Arm(index),
+                arm_env,
+            )))
+        }
+        _ => Err(Error::ParseError(
+            "`yield` can be used only as a statement".to_string(),
+            pos,
+        )),
+    }
+}
+
+// frame for a branch of if or match, which does not consume return
+fn branch_frame(branch: &Node, edge: Edge, env: Environment) -> Frame {
+    let single = !matches!(branch, Node::Sequence(_, _));
+    Frame {
+        kind: Kind::Block {
+            consume_return: false,
+            last: Object::Nil,
+        },
+        path: vec![edge],
+        single,
+        index: 0,
+        env: if single { env } else { env.new_outer() },
+    }
+}
+
+// bind an element of `for` loop. multiple names destructure a list.
+pub fn bind_names(
+    env: &mut Environment,
+    names: &[String],
+    item: Object,
+    pos: Position,
+) -> Result<(), Error> {
+    if names.len() == 1 {
+        env.define(&names[0], item);
+        return Ok(());
+    }
+    match item {
+        Object::List(elements, _) if elements.len() == names.len() => {
+            for (name, element) in names.iter().zip(elements.iter().cloned()) {
+                env.define(name, element);
+            }
+            Ok(())
+        }
+        _ => Err(Error::ValueError(
+            format!("cannot unpack {} into {} variables", item, names.len()),
+            pos,
+        )),
+    }
+}
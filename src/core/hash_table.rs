@@ -0,0 +1,136 @@
+use crate::core::error::Error;
+use crate::core::object::Object;
+use crate::core::parser::Position;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+// hashable object. equal objects (including `1 == 1.0`) have the same hash, and NaN is
+// rejected by `check_hashable` because it is not equal to itself.
+#[derive(Debug, Clone, PartialEq)]
+struct Key(Object);
+
+impl Eq for Key {}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_object(&self.0, state);
+    }
+}
+
+fn hash_object<H: Hasher>(obj: &Object, state: &mut H) {
+    match obj {
+        Object::Nil => 0u8.hash(state),
+        Object::Bool(b) => (1u8, b).hash(state),
+        Object::Int(i) => (2u8, i).hash(state),
+        // integral floats are equal to ints. this also makes -0.0 equal to 0.0.
+        Object::Float(f)
+            if f.fract() == 0.0 && *f >= i64::MIN as f64 && *f < -(i64::MIN as f64) =>
+        {
+            (2u8, *f as i64).hash(state)
+        }
+        Object::Float(f) => (3u8, f.to_bits()).hash(state),
+        // complex numbers with zero imaginary part are equal to floats. adding 0.0 makes -0.0
+        // equal to 0.0.
+        Object::Complex(c) if c.im == 0.0 => hash_object(&Object::Float(c.re), state),
+        Object::Complex(c) => (7u8, (c.re + 0.0).to_bits(), (c.im + 0.0).to_bits()).hash(state),
+        Object::Str(s) => (4u8, s).hash(state),
+        Object::Range(start, end) => (5u8, start, end).hash(state),
+        Object::Tuple(elements) => {
+            (6u8, elements.len()).hash(state);
+            for element in elements.iter() {
+                hash_object(element, state);
+            }
+        }
+        // unhashable objects are never stored
+        _ => unreachable!(),
+    }
+}
+
+// map from hashable objects which keeps the insertion order. removal is O(n) to keep the
+// order.
+#[derive(Debug, Clone, Default)]
+pub struct HashTable {
+    entries: Vec<(Object, Object)>,
+    indices: HashMap<Key, usize>,
+}
+
+impl HashTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn index(&self, key: &Object) -> Option<usize> {
+        if key.check_hashable(Position::new(0, 0)).is_err() {
+            return None;
+        }
+        self.indices.get(&Key(key.clone())).copied()
+    }
+
+    pub fn get(&self, key: &Object) -> Option<&Object> {
+        self.index(key).map(|i| &self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, key: &Object) -> Option<&mut Object> {
+        self.index(key).map(|i| &mut self.entries[i].1)
+    }
+
+    pub fn contains_key(&self, key: &Object) -> bool {
+        self.index(key).is_some()
+    }
+
+    // insert the pair, or overwrite the value keeping the position of the key
+    pub fn insert(&mut self, key: Object, value: Object, pos: Position) -> Result<(), Error> {
+        key.check_hashable(pos)?;
+        match self.indices.get(&Key(key.clone())) {
+            Some(&i) => self.entries[i].1 = value,
+            None => {
+                self.indices.insert(Key(key.clone()), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, key: &Object) -> Option<Object> {
+        let i = self.index(key)?;
+        self.indices.remove(&Key(key.clone()));
+        let (_, value) = self.entries.remove(i);
+        for index in self.indices.values_mut() {
+            if *index > i {
+                *index -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Object, Object)> {
+        self.entries.iter()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Object> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Object> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Object> {
+        self.entries.iter_mut().map(|(_, v)| v)
+    }
+}
+
+// equal if they have the same pairs in any order
+impl PartialEq for HashTable {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
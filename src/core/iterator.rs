@@ -0,0 +1,138 @@
+use crate::core::error::Error;
+use crate::core::gc::Handle;
+use crate::core::generator::Generator;
+use crate::core::object::Object;
+use crate::core::parser::Position;
+use crate::core::string::Str;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+// state of iterator objects. combinators hold their source iterators and take elements
+// lazily.
+#[derive(Debug)]
+pub enum IterState {
+    List(Rc<Vec<Object>>, usize),
+    // chars of the string from the byte offset
+    Str(Str, usize),
+    // next value and end (exclusive)
+    Range(i64, i64),
+    // infinite sequence of next value and step
+    Count(i64, i64),
+    Generator(Generator),
+    Map(Object, Object),
+    Filter(Object, Object),
+    // remaining number of elements
+    Take(Object, usize),
+    // number of elements to be skipped
+    Skip(Object, usize),
+    Enumerate(Object, i64),
+    Zip(Vec<Object>),
+}
+
+impl IterState {
+    pub fn trace(&self, out: &mut Vec<Handle>) {
+        match self {
+            IterState::List(elements, _) => out.push(Handle::Sequence(Rc::downgrade(elements))),
+            IterState::Str(_, _) | IterState::Range(_, _) | IterState::Count(_, _) => {}
+            IterState::Generator(generator) => generator.trace(out),
+            IterState::Map(source, function) | IterState::Filter(source, function) => {
+                source.trace(out);
+                function.trace(out);
+            }
+            IterState::Take(source, _)
+            | IterState::Skip(source, _)
+            | IterState::Enumerate(source, _) => source.trace(out),
+            IterState::Zip(sources) => sources.iter().for_each(|source| source.trace(out)),
+        }
+    }
+
+    pub fn next(&mut self, pos: Position) -> Result<Option<Object>, Error> {
+        match self {
+            IterState::List(elements, index) => {
+                let element = elements.get(*index).cloned();
+                *index += 1;
+                Ok(element)
+            }
+            IterState::Str(s, offset) => match s[*offset..].chars().next() {
+                Some(c) => {
+                    *offset += c.len_utf8();
+                    Ok(Some(Object::Str(c.to_string().into())))
+                }
+                None => Ok(None),
+            },
+            IterState::Range(next, end) => {
+                if next < end {
+                    *next += 1;
+                    Ok(Some(Object::Int(*next - 1)))
+                } else {
+                    Ok(None)
+                }
+            }
+            IterState::Count(next, step) => {
+                let value = *next;
+                *next = next.checked_add(*step).ok_or(Error::OverflowError(
+                    "count exceeded the range of int".to_string(),
+                    pos,
+                ))?;
+                Ok(Some(Object::Int(value)))
+            }
+            IterState::Generator(generator) => generator.resume(Object::Nil, pos),
+            IterState::Map(source, function) => match source.next(pos)? {
+                Some(element) => Ok(Some(function.clone().call(
+                    vec![element],
+                    BTreeMap::new(),
+                    pos,
+                )?)),
+                None => Ok(None),
+            },
+            IterState::Filter(source, function) => {
+                while let Some(element) = source.next(pos)? {
+                    let keep =
+                        function
+                            .clone()
+                            .call(vec![element.clone()], BTreeMap::new(), pos)?;
+                    if keep.to_bool() {
+                        return Ok(Some(element));
+                    }
+                }
+                Ok(None)
+            }
+            IterState::Take(source, remaining) => {
+                if *remaining == 0 {
+                    return Ok(None);
+                }
+                *remaining -= 1;
+                source.next(pos)
+            }
+            IterState::Skip(source, count) => {
+                while *count > 0 {
+                    *count -= 1;
+                    if source.next(pos)?.is_none() {
+                        return Ok(None);
+                    }
+                }
+                source.next(pos)
+            }
+            IterState::Enumerate(source, index) => match source.next(pos)? {
+                Some(element) => {
+                    *index += 1;
+                    Ok(Some(Object::new_list(vec![
+                        Object::Int(*index - 1),
+                        element,
+                    ])))
+                }
+                None => Ok(None),
+            },
+            IterState::Zip(sources) => {
+                let mut elements = vec![];
+                for source in sources.iter() {
+                    match source.next(pos)? {
+                        Some(element) => elements.push(element),
+                        None => return Ok(None),
+                    }
+                }
+                Ok(Some(Object::new_list(elements)))
+            }
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use crate::core::context;
+use crate::core::object::MethodFn;
+use std::collections::HashMap;
+
+// methods of each type such as `"abc".upper()`, keyed by type name and method name. each
+// runtime has its own table in its context.
+pub type MethodTable = HashMap<(String, String), MethodFn>;
+
+// method of the type in the table of the active runtime
+pub fn get_method(type_name: &str, name: &str) -> Option<MethodFn> {
+    context::current()
+        .methods
+        .borrow()
+        .get(&(type_name.to_string(), name.to_string()))
+        .copied()
+}
@@ -1,5 +1,14 @@
+pub mod complex;
+pub mod context;
 pub mod environment;
 pub mod error;
+pub mod gc;
+pub mod generator;
+pub mod hash_table;
+pub mod iterator;
+pub mod method;
 pub mod object;
 pub mod parser;
 pub mod runtime;
+pub mod scheduler;
+pub mod string;
@@ -1,33 +1,222 @@
+use crate::core::complex::Complex;
 use crate::core::environment::Environment;
 use crate::core::error::Error;
+use crate::core::gc::{self, Handle};
+use crate::core::hash_table::HashTable;
+use crate::core::iterator::IterState;
+use crate::core::method::get_method;
 use crate::core::parser::Node;
 use crate::core::parser::Position;
+use crate::core::scheduler::{Queue, Wait};
+use crate::core::string::Str;
 
 use std::cell::RefCell;
-use std::collections::{BTreeMap, HashMap};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::rc::Rc;
 
-#[derive(Debug, Clone, PartialEq)]
+// signature of built-in(Rust) functions
+pub type BuiltInFn = fn(Vec<Object>, BTreeMap<String, Object>, Position) -> Result<Object, Error>;
+
+// signature of methods. the receiver can be modified, and it is written back to the variable.
+pub type MethodFn =
+    fn(&mut Object, Vec<Object>, BTreeMap<String, Object>, Position) -> Result<Object, Error>;
+
+// type defined by `struct`, or variant of `enum`
+#[derive(Debug)]
+pub struct StructDef {
+    pub name: String,
+    pub fields: Vec<String>,
+    pub methods: BTreeMap<String, Object>,
+    // name of the enum if this is a variant
+    pub enum_name: Option<String>,
+}
+
+// type defined by `enum`
+#[derive(Debug)]
+pub struct EnumDef {
+    pub name: String,
+    // constructors (`Object::Struct`) of variants with fields, and instances of unit variants
+    pub variants: Vec<(String, Object)>,
+}
+
+#[derive(Debug, Clone)]
 pub enum Object {
     Nil,
     Bool(bool),
     Int(i64),
     Float(f64),
-    Str(String),
-    List(Vec<Object>),
-    Hash(Vec<Object>, Vec<Object>),
+    Complex(Complex),
+    Str(Str),
+    // containers share their storage until one of them is modified (copy on write), so that
+    // copying them is O(1). the flag is true if the container is frozen by `freeze`, and then
+    // neither it nor its elements can be modified.
+    List(Rc<Vec<Object>>, bool),
+    // pairs of hashable keys and values in insertion order
+    Hash(Rc<HashTable>, bool),
+    // immutable sequence
+    Tuple(Rc<Vec<Object>>),
+    // unique hashable elements in insertion order, which are keys of the table with nil values.
+    // equality ignores the order.
+    Set(Rc<HashTable>, bool),
+    // integers from start (inclusive) to end (exclusive)
+    Range(i64, i64),
     Function {
         name: Option<String>,
         args: Vec<String>,
         kwargs: HashMap<String, Node>,
-        body: Node,
+        body: Rc<Node>,
         env: Environment,
         pos: Position,
     },
-    BuiltInFunction(fn(Vec<Object>, BTreeMap<String, Object>, Position) -> Result<Object, Error>),
+    BuiltInFunction(BuiltInFn),
+    // struct type, which constructs instances when called
+    Struct(Rc<StructDef>),
+    Enum(Rc<EnumDef>),
+    // instance of struct. instances are shared by reference, and fields are in the order of
+    // `StructDef::fields`.
+    Instance {
+        def: Rc<StructDef>,
+        fields: Rc<RefCell<Vec<Object>>>,
+    },
+    // iterators including generators. they are shared by reference.
+    Iterator(Rc<RefCell<IterState>>),
+    // channel between tasks and the host. channels are shared by reference.
+    Channel(Queue),
+    // condition which `await` suspends the task for, such as `sleep(1)`
+    Wait(Wait),
+    // method bound to the receiver, such as `xs.push`
+    Method {
+        receiver: Box<Object>,
+        name: String,
+        method: MethodFn,
+    },
     Return(Box<Object>),
 }
 
+// `==` operator. int and float are equal if they have the same value, hashes are equal if
+// they have the same pairs in any order, and functions are equal only to themselves.
+// instances are compared by `__eq__` if defined, and an error in `__eq__` is unequal here.
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        self.equals(other, Position::new(0, 0)).unwrap_or(false)
+    }
+}
+
+impl Object {
+    fn structural_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Nil, Object::Nil) => true,
+            (Object::Bool(l), Object::Bool(r)) => l == r,
+            (Object::Int(l), Object::Int(r)) => l == r,
+            (Object::Float(l), Object::Float(r)) => l == r,
+            (Object::Int(i), Object::Float(f)) | (Object::Float(f), Object::Int(i)) => {
+                f.fract() == 0.0
+                    && *f >= i64::MIN as f64
+                    && *f < -(i64::MIN as f64)
+                    && *i == *f as i64
+            }
+            // complex numbers are equal to real numbers if the imaginary part is zero
+            (Object::Complex(l), Object::Complex(r)) => l == r,
+            (Object::Complex(c), real @ (Object::Int(_) | Object::Float(_)))
+            | (real @ (Object::Int(_) | Object::Float(_)), Object::Complex(c)) => {
+                c.im == 0.0 && Object::Float(c.re).structural_eq(real)
+            }
+            (Object::Str(l), Object::Str(r)) => l == r,
+            (Object::List(l, _), Object::List(r, _)) => l == r,
+            (Object::Tuple(l), Object::Tuple(r)) => l == r,
+            (Object::Set(l, _), Object::Set(r, _)) => l == r,
+            (Object::Hash(l, _), Object::Hash(r, _)) => l == r,
+            (
+                Object::Function {
+                    env: l_env,
+                    pos: l_pos,
+                    ..
+                },
+                Object::Function {
+                    env: r_env,
+                    pos: r_pos,
+                    ..
+                },
+            ) => l_pos == r_pos && l_env.same_scope(r_env),
+            (Object::BuiltInFunction(l), Object::BuiltInFunction(r)) => {
+                std::ptr::fn_addr_eq(*l, *r)
+            }
+            (
+                Object::Method {
+                    receiver: l_receiver,
+                    method: l_method,
+                    ..
+                },
+                Object::Method {
+                    receiver: r_receiver,
+                    method: r_method,
+                    ..
+                },
+            ) => l_receiver == r_receiver && std::ptr::fn_addr_eq(*l_method, *r_method),
+            (Object::Struct(l), Object::Struct(r)) => Rc::ptr_eq(l, r),
+            (Object::Enum(l), Object::Enum(r)) => Rc::ptr_eq(l, r),
+            (Object::Iterator(l), Object::Iterator(r)) => Rc::ptr_eq(l, r),
+            (Object::Channel(l), Object::Channel(r)) => Rc::ptr_eq(l, r),
+            (Object::Wait(Wait::Sleep(l)), Object::Wait(Wait::Sleep(r))) => l == r,
+            (Object::Wait(Wait::Receive(l)), Object::Wait(Wait::Receive(r))) => Rc::ptr_eq(l, r),
+            (
+                Object::Instance {
+                    def: l_def,
+                    fields: l_fields,
+                },
+                Object::Instance {
+                    def: r_def,
+                    fields: r_fields,
+                },
+            ) => Rc::ptr_eq(l_def, r_def) && fields_eq(l_fields, r_fields),
+            (Object::Range(l_start, l_end), Object::Range(r_start, r_end)) => {
+                l_start == r_start && l_end == r_end
+            }
+            (Object::Return(l), Object::Return(r)) => l == r,
+            _ => false,
+        }
+    }
+}
+
+// compare int and float exactly. converting the int to float may round it, e.g.
+// 9007199254740993 becomes 9007199254740992.0.
+fn compare_int_float(i: i64, f: f64) -> Option<Ordering> {
+    if f.is_nan() {
+        None
+    } else if f >= -(i64::MIN as f64) {
+        Some(Ordering::Less)
+    } else if f < i64::MIN as f64 {
+        Some(Ordering::Greater)
+    } else {
+        // the integral part fits in int, and the fraction decides only if they are equal
+        match i.cmp(&(f.trunc() as i64)) {
+            Ordering::Equal => 0.0.partial_cmp(&f.fract()),
+            ordering => Some(ordering),
+        }
+    }
+}
+
+thread_local! {
+    // pairs of fields being compared, to stop at cycles such as `a.next = a`
+    static COMPARING: RefCell<HashSet<(usize, usize)>> = RefCell::new(HashSet::new());
+}
+
+// fields of instances are equal if they are equal element by element. a pair which is being
+// compared already is regarded as equal, so that cyclic instances of the same shape are equal.
+fn fields_eq(l: &Rc<RefCell<Vec<Object>>>, r: &Rc<RefCell<Vec<Object>>>) -> bool {
+    if Rc::ptr_eq(l, r) {
+        return true;
+    }
+    let pair = (Rc::as_ptr(l) as usize, Rc::as_ptr(r) as usize);
+    if !COMPARING.with(|comparing| comparing.borrow_mut().insert(pair)) {
+        return true;
+    }
+    let result = *l.borrow() == *r.borrow();
+    COMPARING.with(|comparing| comparing.borrow_mut().remove(&pair));
+    result
+}
+
 impl std::fmt::Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
@@ -47,8 +236,9 @@ impl std::fmt::Display for Object {
                     write!(f, "{}.0", v)?
                 }
             }
+            Object::Complex(c) => write!(f, "{}", c)?,
             Object::Str(s) => write!(f, "{:?}", s)?,
-            Object::List(l) => write!(
+            Object::List(l, _) => write!(
                 f,
                 "[{}]",
                 l.iter()
@@ -56,6 +246,35 @@ impl std::fmt::Display for Object {
                     .collect::<Vec<String>>()
                     .join(", ")
             )?,
+            Object::Hash(table, _) => write!(
+                f,
+                "{{{}}}",
+                table
+                    .iter()
+                    .map(|(k, v)| format!("{} -> {}", k, v))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )?,
+            Object::Tuple(t) if t.len() == 1 => write!(f, "({},)", t[0])?,
+            Object::Tuple(t) => write!(
+                f,
+                "({})",
+                t.iter()
+                    .map(|x| format!("{}", x))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )?,
+            Object::Set(elements, _) if elements.is_empty() => write!(f, "set()")?,
+            Object::Set(elements, _) => write!(
+                f,
+                "{{{}}}",
+                elements
+                    .keys()
+                    .map(|x| format!("{}", x))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )?,
+            Object::Range(start, end) => write!(f, "{}..{}", start, end)?,
             Object::Function {
                 name: _,
                 args: _,
@@ -65,6 +284,46 @@ impl std::fmt::Display for Object {
                 pos: _,
             } => write!(f, "<function>")?,
             Object::BuiltInFunction(_func) => write!(f, "<built-in function>")?,
+            Object::Struct(def) => match &def.enum_name {
+                Some(enum_name) => write!(f, "<variant {}.{}>", enum_name, def.name)?,
+                None => write!(f, "<struct {}>", def.name)?,
+            },
+            Object::Enum(def) => write!(f, "<enum {}>", def.name)?,
+            Object::Iterator(_) => write!(f, "<iterator>")?,
+            Object::Channel(_) => write!(f, "<channel>")?,
+            Object::Wait(Wait::Sleep(seconds)) => write!(f, "<wait sleep({})>", seconds)?,
+            Object::Wait(Wait::Receive(_)) => write!(f, "<wait recv>")?,
+            // unit variant of enum
+            Object::Instance { def, .. } if def.enum_name.is_some() && def.fields.is_empty() => {
+                write!(f, "{}", def.name)?
+            }
+            Object::Instance { def, .. } if def.methods.contains_key("__str__") => {
+                match self.call_special("__str__", vec![], Position::new(0, 0)) {
+                    Some(Ok(Object::Str(s))) => write!(f, "{}", s)?,
+                    _ => write!(f, "<{} object>", def.name)?,
+                }
+            }
+            Object::Instance { def, fields } => {
+                // an instance which contains itself is displayed as `Name(...)`
+                let fields = match fields.try_borrow_mut() {
+                    Ok(fields) => fields,
+                    Err(_) => return write!(f, "{}(...)", def.name),
+                };
+                write!(
+                    f,
+                    "{}({})",
+                    def.name,
+                    def.fields
+                        .iter()
+                        .zip(fields.iter())
+                        .map(|(name, value)| format!("{}={}", name, value))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )?
+            }
+            Object::Method { receiver, name, .. } => {
+                write!(f, "<method {}.{}>", receiver.type_name(), name)?
+            }
             _ => write!(f, "<unknown object>")?,
         };
         Ok(())
@@ -83,12 +342,23 @@ impl Object {
         match self {
             Object::Bool(_) => "bool",
             Object::Float(_) => "float",
+            Object::Complex(_) => "complex",
             Object::Nil => "nil",
             Object::Int(_) => "int",
             Object::Str(_) => "str",
-            Object::List(_) => "list",
+            Object::List(_, _) => "list",
             Object::Hash(_, _) => "hash",
+            Object::Tuple(_) => "tuple",
+            Object::Set(_, _) => "set",
+            Object::Range(_, _) => "range",
             Object::BuiltInFunction(_) => "function",
+            Object::Method { .. } => "function",
+            Object::Struct(_) => "struct",
+            Object::Enum(_) => "enum",
+            Object::Iterator(_) => "iterator",
+            Object::Channel(_) => "channel",
+            Object::Wait(_) => "wait",
+            Object::Instance { def, .. } => def.enum_name.as_ref().unwrap_or(&def.name),
             Object::Function {
                 name: _,
                 args: _,
@@ -102,36 +372,482 @@ impl Object {
     }
 
     // for if-expression
-    pub fn to_bool(self) -> bool {
+    pub fn to_bool(&self) -> bool {
         match self {
-            Object::Bool(b) => b,
+            Object::Bool(b) => *b,
             Object::Nil => false,
             _ => true,
         }
     }
 
     // Operators
-    pub fn less_than(self, other: Self, pos: Position) -> Result<Object, Error> {
+
+    // call the object with evaluated arguments
+    pub fn call(
+        self,
+        args: Vec<Object>,
+        kwargs: BTreeMap<String, Object>,
+        pos: Position,
+    ) -> Result<Object, Error> {
         match self {
-            Object::Int(l) => match other {
-                Object::Int(r) => Ok(Object::Bool(l < r)),
-                Object::Float(r) => Ok(Object::Bool((l as f64) < r)),
-                _ => Err(Error::TypeError(
-                    format!("cannot compare int < {}", other.type_name()),
+            Object::BuiltInFunction(func) => func(args, kwargs, pos),
+            // a method called apart from its receiver, such as `f = xs.push; f(1)`. there is no
+            // place to write the receiver back to, so modifying it is an error. the original is
+            // kept to share the container, so that a modification makes a new copy.
+            Object::Method {
+                mut receiver,
+                name,
+                method,
+            } => {
+                let original = receiver.clone();
+                let result = method(&mut receiver, args, kwargs, pos)?;
+                if !receiver.same_container(&original) {
+                    return Err(Error::TypeError(
+                        format!(
+                            "method `{}` modifies {}, so it must be called on it directly",
+                            name,
+                            receiver.type_name()
+                        ),
+                        pos,
+                    ));
+                }
+                Ok(result)
+            }
+            Object::Struct(def) => construct(def, args, kwargs, pos),
+            Object::Function { ref env, .. } => env.clone().call_object(self, args, kwargs, pos),
+            _ => Err(Error::TypeError(
+                format!("{} is not callable.", self.type_name()),
+                pos,
+            )),
+        }
+    }
+
+    // call the special method such as `__add__` if the object is an instance which defines it
+    pub fn call_special(
+        &self,
+        name: &str,
+        args: Vec<Object>,
+        pos: Position,
+    ) -> Option<Result<Object, Error>> {
+        match self {
+            Object::Instance { def, .. } => def.methods.get(name).map(|method| {
+                method
+                    .bind_self(self.clone())
+                    .call(args, BTreeMap::new(), pos)
+            }),
+            _ => None,
+        }
+    }
+
+    // text of `str(x)`, which is not quoted for str. errors of `__str__` are propagated unlike
+    // Display, which falls back to `<Name object>` for debug printing.
+    pub fn to_text(&self, pos: Position) -> Result<String, Error> {
+        match self {
+            Object::Str(s) => Ok(s.to_string()),
+            _ => match self.call_special("__str__", vec![], pos) {
+                Some(result) => match result? {
+                    Object::Str(s) => Ok(s.to_string()),
+                    _ => Err(Error::TypeError("__str__ must return str".to_string(), pos)),
+                },
+                None => Ok(self.to_string()),
+            },
+        }
+    }
+
+    // `__add__` of the left operand, or `__radd__` of the right operand
+    fn binary_special(
+        &self,
+        other: &Object,
+        name: &str,
+        pos: Position,
+    ) -> Option<Result<Object, Error>> {
+        self.call_special(&format!("__{}__", name), vec![other.clone()], pos)
+            .or_else(|| other.call_special(&format!("__r{}__", name), vec![self.clone()], pos))
+    }
+
+    // `==` which consults `__eq__` of instances
+    pub fn equals(&self, other: &Object, pos: Position) -> Result<bool, Error> {
+        match self.call_special("__eq__", vec![other.clone()], pos) {
+            Some(result) => Ok(result?.to_bool()),
+            None => match other.call_special("__eq__", vec![self.clone()], pos) {
+                Some(result) => Ok(result?.to_bool()),
+                None => Ok(self.structural_eq(other)),
+            },
+        }
+    }
+
+    // function whose `self` is bound to the instance
+    fn bind_self(&self, instance: Object) -> Object {
+        match self {
+            Object::Function {
+                name,
+                args,
+                kwargs,
+                body,
+                env,
+                pos,
+            } => {
+                let mut env = env.clone().detach();
+                env.define("self", instance);
+                Object::Function {
+                    name: name.clone(),
+                    args: args.clone(),
+                    kwargs: kwargs.clone(),
+                    body: body.clone(),
+                    env,
+                    pos: *pos,
+                }
+            }
+            _ => self.clone(),
+        }
+    }
+
+    pub fn new_iterator(state: IterState) -> Object {
+        let state = Rc::new(RefCell::new(state));
+        gc::track(Handle::Iterator(Rc::downgrade(&state)));
+        Object::Iterator(state)
+    }
+
+    // objects which can be a part of a cycle, directly referenced by this object
+    pub fn trace(&self, out: &mut Vec<Handle>) {
+        match self {
+            Object::List(l, _) | Object::Tuple(l) => out.push(Handle::Sequence(Rc::downgrade(l))),
+            Object::Hash(table, _) | Object::Set(table, _) => {
+                out.push(Handle::Table(Rc::downgrade(table)))
+            }
+            Object::Function { env, .. } => env.trace(out),
+            Object::Struct(def) => out.push(Handle::Struct(Rc::downgrade(def))),
+            Object::Enum(def) => out.push(Handle::Enum(Rc::downgrade(def))),
+            Object::Instance { def, fields } => {
+                out.push(Handle::Struct(Rc::downgrade(def)));
+                out.push(Handle::Fields(Rc::downgrade(fields)));
+            }
+            Object::Iterator(state) => out.push(Handle::Iterator(Rc::downgrade(state))),
+            Object::Channel(queue) | Object::Wait(Wait::Receive(queue)) => {
+                out.push(Handle::Queue(Rc::downgrade(queue)))
+            }
+            Object::Method { receiver, .. } => receiver.trace(out),
+            Object::Return(value) => value.trace(out),
+            _ => {}
+        }
+    }
+
+    // iterator over the elements. iterators return themselves, and instances can define
+    // `__iter__` which returns an iterable object.
+    pub fn iter(&self, pos: Position) -> Result<Object, Error> {
+        if let Some(result) = self.call_special("__iter__", vec![], pos) {
+            return result?.iter(pos);
+        }
+        match self {
+            Object::Iterator(_) => Ok(self.clone()),
+            Object::List(l, _) | Object::Tuple(l) => {
+                Ok(Object::new_iterator(IterState::List(l.clone(), 0)))
+            }
+            Object::Set(elements, _) => Ok(Object::new_iterator(IterState::List(
+                Rc::new(elements.keys().cloned().collect()),
+                0,
+            ))),
+            Object::Str(s) => Ok(Object::new_iterator(IterState::Str(s.clone(), 0))),
+            // pairs of key and value, which can be unpacked by `for k, v in h`
+            Object::Hash(table, _) => Ok(Object::new_iterator(IterState::List(
+                Rc::new(
+                    table
+                        .iter()
+                        .map(|(k, v)| Object::new_list(vec![k.clone(), v.clone()]))
+                        .collect(),
+                ),
+                0,
+            ))),
+            Object::Range(start, end) => Ok(Object::new_iterator(IterState::Range(*start, *end))),
+            _ => Err(Error::TypeError(
+                format!("{} is not iterable", self.type_name()),
+                pos,
+            )),
+        }
+    }
+
+    // take the next element of the iterator. `None` means the iterator is exhausted.
+    pub fn next(&self, pos: Position) -> Result<Option<Object>, Error> {
+        match self {
+            Object::Iterator(state) => match state.try_borrow_mut() {
+                Ok(mut state) => state.next(pos),
+                Err(_) => Err(Error::ValueError(
+                    "iterator is already running".to_string(),
                     pos,
                 )),
             },
-            Object::Float(l) => match other {
-                Object::Int(r) => Ok(Object::Bool(l < r as f64)),
-                Object::Float(r) => Ok(Object::Bool(l < r)),
-                _ => Err(Error::TypeError(
-                    format!("cannot compare float < {}", other.type_name()),
+            _ => Err(Error::TypeError(
+                format!("{} is not an iterator", self.type_name()),
+                pos,
+            )),
+        }
+    }
+
+    // send the value to the suspended generator and take the next yielded value. `None` means
+    // the generator is finished.
+    pub fn resume(&self, sent: Object, pos: Position) -> Result<Option<Object>, Error> {
+        match self {
+            Object::Iterator(state) => match state.try_borrow_mut() {
+                Ok(mut state) => match &mut *state {
+                    IterState::Generator(generator) => generator.resume(sent, pos),
+                    _ => Err(Error::TypeError(
+                        "only generators can be resumed with a value".to_string(),
+                        pos,
+                    )),
+                },
+                Err(_) => Err(Error::ValueError(
+                    "iterator is already running".to_string(),
                     pos,
                 )),
             },
+            _ => Err(Error::TypeError(
+                format!("{} is not a generator", self.type_name()),
+                pos,
+            )),
+        }
+    }
+
+    // whether the object can be a hash key or an element of set. hashable objects are
+    // immutable, so that they are never changed after added. NaN is not hashable since it is
+    // not equal to itself.
+    pub fn check_hashable(&self, pos: Position) -> Result<(), Error> {
+        match self {
+            Object::Float(f) if f.is_nan() => Err(Error::ValueError(
+                "NaN cannot be a hash key or an element of set".to_string(),
+                pos,
+            )),
+            Object::Complex(c) if c.re.is_nan() || c.im.is_nan() => Err(Error::ValueError(
+                "NaN cannot be a hash key or an element of set".to_string(),
+                pos,
+            )),
+            Object::Nil
+            | Object::Bool(_)
+            | Object::Int(_)
+            | Object::Float(_)
+            | Object::Complex(_)
+            | Object::Str(_)
+            | Object::Range(_, _) => Ok(()),
+            Object::Tuple(elements) => elements.iter().try_for_each(|x| x.check_hashable(pos)),
+            _ => Err(Error::TypeError(
+                format!("{} is unhashable", self.type_name()),
+                pos,
+            )),
+        }
+    }
+
+    // whether this is a container frozen by `freeze`
+    pub fn is_frozen(&self) -> bool {
+        matches!(
+            self,
+            Object::List(_, true) | Object::Hash(_, true) | Object::Set(_, true)
+        )
+    }
+
+    // whether both are the same list, hash or set, rather than copies of it. other objects
+    // are regarded as the same.
+    fn same_container(&self, other: &Object) -> bool {
+        match (self, other) {
+            (Object::List(l, _), Object::List(r, _)) => Rc::ptr_eq(l, r),
+            (Object::Hash(l, _), Object::Hash(r, _)) => Rc::ptr_eq(l, r),
+            (Object::Set(l, _), Object::Set(r, _)) => Rc::ptr_eq(l, r),
+            _ => true,
+        }
+    }
+
+    // error if this is a frozen container, which is checked before modifying it
+    pub fn check_mutable(&self, pos: Position) -> Result<(), Error> {
+        if self.is_frozen() {
+            Err(Error::ImmutableError(
+                format!("cannot modify frozen {}", self.type_name()),
+                pos,
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    // copy which cannot be modified, and whose elements are also frozen. instances and other
+    // objects shared by reference are not frozen.
+    pub fn freeze(self) -> Object {
+        let freeze_all = |elements: &mut Rc<Vec<Object>>| {
+            for element in Rc::make_mut(elements) {
+                *element = std::mem::replace(element, Object::Nil).freeze();
+            }
+        };
+        match self {
+            Object::List(mut l, false) => {
+                freeze_all(&mut l);
+                Object::List(l, true)
+            }
+            Object::Tuple(mut l) => {
+                freeze_all(&mut l);
+                Object::Tuple(l)
+            }
+            Object::Hash(mut table, false) => {
+                for value in Rc::make_mut(&mut table).values_mut() {
+                    *value = std::mem::replace(value, Object::Nil).freeze();
+                }
+                Object::Hash(table, true)
+            }
+            // elements of sets are hashable, which are immutable
+            Object::Set(table, _) => Object::Set(table, true),
+            other => other,
+        }
+    }
+
+    // whether both are the same object. containers are compared by their storage, which is
+    // cheaper than `==` and tells whether one of them is modified.
+    pub fn is_identical(&self, other: &Object) -> bool {
+        match (self, other) {
+            (Object::List(l, l_frozen), Object::List(r, r_frozen)) => {
+                l_frozen == r_frozen && Rc::ptr_eq(l, r)
+            }
+            (Object::Hash(l, l_frozen), Object::Hash(r, r_frozen))
+            | (Object::Set(l, l_frozen), Object::Set(r, r_frozen)) => {
+                l_frozen == r_frozen && Rc::ptr_eq(l, r)
+            }
+            (Object::Tuple(l), Object::Tuple(r)) => Rc::ptr_eq(l, r),
+            (Object::Instance { fields: l, .. }, Object::Instance { fields: r, .. }) => {
+                Rc::ptr_eq(l, r)
+            }
+            _ => self.structural_eq(other),
+        }
+    }
+
+    // set of hashable elements. duplicated elements are removed.
+    pub fn new_set(
+        elements: impl IntoIterator<Item = Object>,
+        pos: Position,
+    ) -> Result<Object, Error> {
+        let mut table = HashTable::new();
+        for element in elements {
+            table.insert(element, Object::Nil, pos)?;
+        }
+        Ok(Object::Set(Rc::new(table), false))
+    }
+
+    pub fn new_list(elements: Vec<Object>) -> Object {
+        Object::List(Rc::new(elements), false)
+    }
+
+    pub fn new_tuple(elements: Vec<Object>) -> Object {
+        Object::Tuple(Rc::new(elements))
+    }
+
+    // `item in self`
+    pub fn contains(&self, item: &Object, pos: Position) -> Result<bool, Error> {
+        if let Some(result) = self.call_special("__contains__", vec![item.clone()], pos) {
+            return Ok(result?.to_bool());
+        }
+        match (self, item) {
+            (Object::List(l, _), _) | (Object::Tuple(l), _) => Ok(l.contains(item)),
+            (Object::Set(table, _), _) | (Object::Hash(table, _), _) => {
+                Ok(table.contains_key(item))
+            }
+            (Object::Str(s), Object::Str(sub)) => Ok(s.contains(sub.as_str())),
+            (Object::Str(_), _) => Err(Error::TypeError(
+                format!("`in <str>` requires str, given {}", item.type_name()),
+                pos,
+            )),
+            (Object::Range(start, end), Object::Int(i)) => Ok(start <= i && i < end),
+            (Object::Range(start, end), Object::Float(f)) => Ok(matches!(
+                compare_int_float(*start, *f),
+                Some(Ordering::Less | Ordering::Equal)
+            ) && compare_int_float(*end, *f)
+                == Some(Ordering::Greater)),
+            (Object::Range(_, _), _) => Ok(false),
+            _ => Err(Error::TypeError(
+                format!("cannot check membership in {}", self.type_name()),
+                pos,
+            )),
+        }
+    }
+
+    // `self is other`. containers, instances, functions, iterators and channels are identical
+    // only if they are the same object, and other objects are identical if they have the same
+    // type and the same value.
+    pub fn is(&self, other: &Object) -> bool {
+        self.type_name() == other.type_name() && self.is_identical(other)
+    }
+
+    // Ordering of objects: int and float are compared by value, str lexicographically by
+    // unicode code point, list element-wise (a shorter prefix comes first) and bool as
+    // false < true. Other combinations cannot be compared. `None` means unordered (NaN).
+    pub fn partial_compare(&self, other: &Self, pos: Position) -> Result<Option<Ordering>, Error> {
+        self.ordering(other, false, pos)
+    }
+
+    // Total ordering used by sorting builtins. NaN is greater than any other number.
+    pub fn compare(&self, other: &Self, pos: Position) -> Result<Ordering, Error> {
+        Ok(self.ordering(other, true, pos)?.unwrap())
+    }
+
+    fn ordering(
+        &self,
+        other: &Self,
+        total: bool,
+        pos: Position,
+    ) -> Result<Option<Ordering>, Error> {
+        // instances are ordered by `__lt__`
+        if let Some(less) = self.call_special("__lt__", vec![other.clone()], pos) {
+            if less?.to_bool() {
+                return Ok(Some(Ordering::Less));
+            }
+            return match other.call_special("__lt__", vec![self.clone()], pos) {
+                Some(greater) => Ok(Some(if greater?.to_bool() {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                })),
+                None => Ok(Some(Ordering::Greater)),
+            };
+        }
+        if let Some(greater) = other.call_special("__lt__", vec![self.clone()], pos) {
+            return Ok(Some(if greater?.to_bool() {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }));
+        }
+        match (self, other) {
+            (Object::Int(l), Object::Int(r)) => Ok(Some(l.cmp(r))),
+            (Object::Int(_), Object::Float(_))
+            | (Object::Float(_), Object::Int(_))
+            | (Object::Float(_), Object::Float(_)) => {
+                let ordering = match (self, other) {
+                    (Object::Int(i), Object::Float(f)) => compare_int_float(*i, *f),
+                    (Object::Float(f), Object::Int(i)) => {
+                        compare_int_float(*i, *f).map(Ordering::reverse)
+                    }
+                    _ => self.to_f64().partial_cmp(&other.to_f64()),
+                };
+                if total {
+                    Ok(Some(
+                        match (self.to_f64().is_nan(), other.to_f64().is_nan()) {
+                            (true, true) => Ordering::Equal,
+                            (true, false) => Ordering::Greater,
+                            (false, true) => Ordering::Less,
+                            (false, false) => ordering.unwrap(),
+                        },
+                    ))
+                } else {
+                    Ok(ordering)
+                }
+            }
+            (Object::Str(l), Object::Str(r)) => Ok(Some(l.cmp(r))),
+            (Object::Bool(l), Object::Bool(r)) => Ok(Some(l.cmp(r))),
+            (Object::List(l, _), Object::List(r, _)) | (Object::Tuple(l), Object::Tuple(r)) => {
+                for (a, b) in l.iter().zip(r.iter()) {
+                    match a.ordering(b, total, pos)? {
+                        Some(Ordering::Equal) => {}
+                        ordering => return Ok(ordering),
+                    }
+                }
+                Ok(Some(l.len().cmp(&r.len())))
+            }
             _ => Err(Error::TypeError(
                 format!(
-                    "cannot compare {} < {}",
+                    "cannot compare {} and {}",
                     self.type_name(),
                     other.type_name()
                 ),
@@ -140,41 +856,212 @@ impl Object {
         }
     }
 
+    pub fn less_than(self, other: Self, pos: Position) -> Result<Object, Error> {
+        let ordering = self.partial_compare(&other, pos)?;
+        Ok(Object::Bool(ordering == Some(Ordering::Less)))
+    }
+
+    pub fn less_than_eq(self, other: Self, pos: Position) -> Result<Object, Error> {
+        let ordering = self.partial_compare(&other, pos)?;
+        Ok(Object::Bool(matches!(
+            ordering,
+            Some(Ordering::Less | Ordering::Equal)
+        )))
+    }
+
+    pub fn greater_than(self, other: Self, pos: Position) -> Result<Object, Error> {
+        let ordering = self.partial_compare(&other, pos)?;
+        Ok(Object::Bool(ordering == Some(Ordering::Greater)))
+    }
+
+    pub fn greater_than_eq(self, other: Self, pos: Position) -> Result<Object, Error> {
+        let ordering = self.partial_compare(&other, pos)?;
+        Ok(Object::Bool(matches!(
+            ordering,
+            Some(Ordering::Greater | Ordering::Equal)
+        )))
+    }
+
+    // `a.b`, which is resolved to a method if there is no such attribute
     pub fn get_attribute(self, index: Self, pos: Position) -> Result<Object, Error> {
+        self.get_element(index, true, pos)
+    }
+
+    // `a[i]`, which is never resolved to a method
+    pub fn get_index(self, index: Self, pos: Position) -> Result<Object, Error> {
+        self.get_element(index, false, pos)
+    }
+
+    fn get_element(self, index: Self, methods: bool, pos: Position) -> Result<Object, Error> {
+        if let (Object::Enum(def), Object::Str(name)) = (&self, &index) {
+            return match def.variants.iter().find(|(variant, _)| name == variant) {
+                Some((_, variant)) => Ok(variant.clone()),
+                None => Err(Error::AttributeError(
+                    format!("enum {} has no variant `{}`", def.name, name),
+                    pos,
+                )),
+            };
+        }
+        if let (Object::Instance { def, fields }, Object::Str(name)) = (&self, &index) {
+            if let Some(i) = def.fields.iter().position(|field| name == field) {
+                return Ok(fields.borrow()[i].clone());
+            }
+            if let Some(method) = def.methods.get(name.as_str()) {
+                return Ok(method.bind_self(self.clone()));
+            }
+        }
+        if let Some(result) = self.call_special("__index__", vec![index.clone()], pos) {
+            return result;
+        }
+        // keys of hash take precedence over methods
+        if let (true, Object::Str(name)) = (methods, &index) {
+            let is_key = matches!(&self, Object::Hash(table, _) if table.contains_key(&index));
+            if !is_key {
+                return match get_method(self.type_name(), name) {
+                    Some(method) => Ok(Object::Method {
+                        receiver: Box::new(self),
+                        name: name.to_string(),
+                        method,
+                    }),
+                    None if matches!(self, Object::Hash(_, _)) => {
+                        Err(Error::KeyError(format!("key {} is not found", index), pos))
+                    }
+                    None => Err(Error::AttributeError(
+                        format!("{} has no attribute `{}`", self.type_name(), name),
+                        pos,
+                    )),
+                };
+            }
+        }
         match self {
-            Object::List(l) => match index {
+            Object::List(ref l, _) | Object::Tuple(ref l) => match index {
                 Object::Int(i) => {
-                    let mut idx = i as usize;
-                    if i < 0 {
-                        idx = (-i) as usize;
-                    }
                     if (i as usize) < l.len() {
-                        Ok(l[idx as usize].clone())
+                        Ok(l[i as usize].clone())
                     } else {
                         Err(Error::IndexOutOfRange(
-                            format!("index {} is out of range", i.to_string()),
+                            format!("index {} is out of range", i),
                             pos,
                         ))
                     }
                 }
+                // slice of the same type
+                Object::Range(start, end) => {
+                    let elements = slice_bounds(start, end, l.len(), pos)
+                        .map(|(start, end)| l[start..end].to_vec())?;
+                    match self {
+                        Object::Tuple(_) => Ok(Object::new_tuple(elements)),
+                        _ => Ok(Object::new_list(elements)),
+                    }
+                }
                 _ => Err(Error::TypeError(
                     format!("index must be int, given {}", index.type_name()),
                     pos,
                 )),
             },
+            // strings are indexed by chars (unicode scalar values)
             Object::Str(s) => match index {
-                Object::Int(i) => {
-                    let mut idx = i as usize;
-                    if i < 0 {
-                        idx = (-i) as usize;
+                Object::Int(i) => match s.char_at(i as usize) {
+                    Some(c) => Ok(Object::Str(c.into())),
+                    None => Err(Error::IndexOutOfRange(
+                        format!("index {} is out of range", i),
+                        pos,
+                    )),
+                },
+                Object::Range(start, end) => {
+                    let (start, end) = slice_bounds(start, end, s.char_count(), pos)?;
+                    Ok(Object::Str(
+                        s.char_slice(start, end).unwrap_or_default().into(),
+                    ))
+                }
+                _ => Err(Error::TypeError(
+                    format!("index must be int, given {}", index.type_name()),
+                    pos,
+                )),
+            },
+            Object::Hash(table, _) => match table.get(&index) {
+                Some(value) => Ok(value.clone()),
+                None => Err(Error::KeyError(format!("key {} is not found", index), pos)),
+            },
+            _ => Err(Error::TypeError(
+                format!(
+                    "cannot calculate {}[{}]",
+                    self.type_name(),
+                    index.type_name()
+                ),
+                pos,
+            )),
+        }
+    }
+
+    // move the list, hash or set at `index` out of this list or hash leaving nil, so that it
+    // can be modified without copying. returns None if there is no such element.
+    pub fn take_element(&mut self, index: &Object) -> Option<Object> {
+        let is_container = |obj: &Object| {
+            matches!(
+                obj,
+                Object::List(_, _) | Object::Hash(_, _) | Object::Set(_, _)
+            )
+        };
+        let element = match (self, index) {
+            (Object::List(l, false), Object::Int(i))
+                if l.get(*i as usize).is_some_and(is_container) =>
+            {
+                &mut Rc::make_mut(l)[*i as usize]
+            }
+            (Object::Hash(table, false), _) if table.get(index).is_some_and(is_container) => {
+                Rc::make_mut(table).get_mut(index)?
+            }
+            _ => return None,
+        };
+        Some(std::mem::replace(element, Object::Nil))
+    }
+
+    // replace the element at `index` with `value`. the container is unchanged on error.
+    pub fn set_attribute(&mut self, index: Self, value: Self, pos: Position) -> Result<(), Error> {
+        // receivers of method calls are written back after the call, which is allowed for
+        // immutable containers if the receiver is not modified
+        if self.is_frozen() || matches!(self, Object::Tuple(_)) {
+            let current = match (&*self, &index) {
+                (Object::List(l, _) | Object::Tuple(l), Object::Int(i)) => l.get(*i as usize),
+                (Object::Hash(table, _), _) => table.get(&index),
+                _ => None,
+            };
+            if current.is_some_and(|current| current.is_identical(&value)) {
+                return Ok(());
+            }
+            self.check_mutable(pos)?;
+        }
+        match self {
+            Object::Instance { def, fields } => {
+                let i = match &index {
+                    Object::Str(name) => def.fields.iter().position(|field| name == field),
+                    _ => None,
+                };
+                match i {
+                    Some(i) => {
+                        fields.borrow_mut()[i] = value;
+                        Ok(())
                     }
-                    if (i as usize) < s.len() {
-                        Ok(Object::Str(
-                            s.chars().collect::<Vec<char>>()[i as usize].to_string(),
-                        ))
+                    None if def.methods.contains_key("__setindex__") => {
+                        self.call_special("__setindex__", vec![index, value], pos)
+                            .unwrap()?;
+                        Ok(())
+                    }
+                    None => Err(Error::AttributeError(
+                        format!("{} has no field {}", def.name, index),
+                        pos,
+                    )),
+                }
+            }
+            Object::List(l, _) => match index {
+                Object::Int(i) => {
+                    if i >= 0 && (i as usize) < l.len() {
+                        Rc::make_mut(l)[i as usize] = value;
+                        Ok(())
                     } else {
                         Err(Error::IndexOutOfRange(
-                            format!("index {} is out of range", i.to_string()),
+                            format!("index {} is out of range", i),
                             pos,
                         ))
                     }
@@ -184,9 +1071,13 @@ impl Object {
                     pos,
                 )),
             },
+            Object::Hash(table, _) => {
+                index.check_hashable(pos)?;
+                Rc::make_mut(table).insert(index, value, pos)
+            }
             _ => Err(Error::TypeError(
                 format!(
-                    "cannot calculate {}[{}]",
+                    "cannot assign to {}[{}]",
                     self.type_name(),
                     index.type_name()
                 ),
@@ -195,27 +1086,57 @@ impl Object {
         }
     }
 
-    pub fn less_than_eq(self, other: Self, pos: Position) -> Result<Object, Error> {
+    pub fn neg(self, pos: Position) -> Result<Object, Error> {
+        if let Some(result) = self.call_special("__neg__", vec![], pos) {
+            return result;
+        }
         match self {
-            Object::Int(l) => match other {
-                Object::Int(r) => Ok(Object::Bool(l <= r)),
-                Object::Float(r) => Ok(Object::Bool((l as f64) <= r)),
-                _ => Err(Error::TypeError(
-                    format!("cannot compare int <= {}", other.type_name()),
-                    pos,
-                )),
-            },
-            Object::Float(l) => match other {
-                Object::Int(r) => Ok(Object::Bool(l <= r as f64)),
-                Object::Float(r) => Ok(Object::Bool(l <= r)),
-                _ => Err(Error::TypeError(
-                    format!("cannot compare float <= {}", other.type_name()),
-                    pos,
-                )),
-            },
+            Object::Int(v) => Ok(Object::Int(
+                v.checked_neg().ok_or_else(|| overflow("-", pos))?,
+            )),
+            Object::Float(v) => Ok(Object::Float(-v)),
+            Object::Complex(c) => Ok(Object::Complex(-c)),
+            _ => Err(Error::TypeError(
+                format!("cannot make negative {}", self.type_name()),
+                pos,
+            )),
+        }
+    }
+
+    // the result has the same sign as the divisor.
+    pub fn modulo(self, other: Self, pos: Position) -> Result<Object, Error> {
+        if let Some(result) = self.binary_special(&other, "mod", pos) {
+            return result;
+        }
+        match other {
+            Object::Int(0i64) | Object::Float(0.0f64) => {
+                return Err(Error::DivideByZero("modulo by zero".to_string(), pos))
+            }
+            _ => {}
+        }
+        match (&self, &other) {
+            (Object::Int(l), Object::Int(r)) => {
+                let m = l.wrapping_rem(*r);
+                if m != 0 && (m < 0) != (*r < 0) {
+                    Ok(Object::Int(m + r))
+                } else {
+                    Ok(Object::Int(m))
+                }
+            }
+            (Object::Int(_), Object::Float(_))
+            | (Object::Float(_), Object::Int(_))
+            | (Object::Float(_), Object::Float(_)) => {
+                let (l, r) = (self.to_f64(), other.to_f64());
+                let m = l % r;
+                if m != 0.0 && (m < 0.0) != (r < 0.0) {
+                    Ok(Object::Float(m + r))
+                } else {
+                    Ok(Object::Float(m))
+                }
+            }
             _ => Err(Error::TypeError(
                 format!(
-                    "cannot compare {} <= {}",
+                    "cannot calculate {} % {}",
                     self.type_name(),
                     other.type_name()
                 ),
@@ -224,27 +1145,34 @@ impl Object {
         }
     }
 
-    pub fn greater_than(self, other: Self, pos: Position) -> Result<Object, Error> {
-        match self {
-            Object::Int(l) => match other {
-                Object::Int(r) => Ok(Object::Bool(l > r)),
-                Object::Float(r) => Ok(Object::Bool((l as f64) > r)),
-                _ => Err(Error::TypeError(
-                    format!("cannot compare int > {}", other.type_name()),
-                    pos,
-                )),
-            },
-            Object::Float(l) => match other {
-                Object::Int(r) => Ok(Object::Bool(l > r as f64)),
-                Object::Float(r) => Ok(Object::Bool(l > r)),
-                _ => Err(Error::TypeError(
-                    format!("cannot compare float > {}", other.type_name()),
-                    pos,
-                )),
-            },
+    // rounds the quotient toward negative infinity.
+    pub fn floor_div(self, other: Self, pos: Position) -> Result<Object, Error> {
+        if let Some(result) = self.binary_special(&other, "floordiv", pos) {
+            return result;
+        }
+        match other {
+            Object::Int(0i64) | Object::Float(0.0f64) => {
+                return Err(Error::DivideByZero("divide by zero".to_string(), pos))
+            }
+            _ => {}
+        }
+        match (&self, &other) {
+            (Object::Int(l), Object::Int(r)) => {
+                let q = l.checked_div(*r).ok_or_else(|| overflow("//", pos))?;
+                if l % r != 0 && (*l < 0) != (*r < 0) {
+                    Ok(Object::Int(q - 1))
+                } else {
+                    Ok(Object::Int(q))
+                }
+            }
+            (Object::Int(_), Object::Float(_))
+            | (Object::Float(_), Object::Int(_))
+            | (Object::Float(_), Object::Float(_)) => {
+                Ok(Object::Float((self.to_f64() / other.to_f64()).floor()))
+            }
             _ => Err(Error::TypeError(
                 format!(
-                    "cannot compare {} > {}",
+                    "cannot calculate {} // {}",
                     self.type_name(),
                     other.type_name()
                 ),
@@ -253,27 +1181,19 @@ impl Object {
         }
     }
 
-    pub fn greater_than_eq(self, other: Self, pos: Position) -> Result<Object, Error> {
-        match self {
-            Object::Int(l) => match other {
-                Object::Int(r) => Ok(Object::Bool(l >= r)),
-                Object::Float(r) => Ok(Object::Bool((l as f64) >= r)),
-                _ => Err(Error::TypeError(
-                    format!("cannot compare int >= {}", other.type_name()),
-                    pos,
-                )),
-            },
-            Object::Float(l) => match other {
-                Object::Int(r) => Ok(Object::Bool(l >= r as f64)),
-                Object::Float(r) => Ok(Object::Bool(l >= r)),
-                _ => Err(Error::TypeError(
-                    format!("cannot compare float >= {}", other.type_name()),
-                    pos,
-                )),
-            },
+    pub fn bit_and(self, other: Self, pos: Position) -> Result<Object, Error> {
+        if let Some(result) = self.binary_special(&other, "and", pos) {
+            return result;
+        }
+        match (&self, &other) {
+            (Object::Set(l, _), Object::Set(r, _)) => {
+                Object::new_set(l.keys().filter(|x| r.contains_key(x)).cloned(), pos)
+            }
+            (Object::Int(l), Object::Int(r)) => Ok(Object::Int(l & r)),
+            (Object::Bool(l), Object::Bool(r)) => Ok(Object::Bool(l & r)),
             _ => Err(Error::TypeError(
                 format!(
-                    "cannot compare {} >= {}",
+                    "cannot calculate {} & {}",
                     self.type_name(),
                     other.type_name()
                 ),
@@ -282,18 +1202,147 @@ impl Object {
         }
     }
 
-    pub fn neg(self, pos: Position) -> Result<Object, Error> {
+    pub fn bit_or(self, other: Self, pos: Position) -> Result<Object, Error> {
+        if let Some(result) = self.binary_special(&other, "or", pos) {
+            return result;
+        }
+        match (&self, &other) {
+            (Object::Set(l, _), Object::Set(r, _)) => {
+                Object::new_set(l.keys().chain(r.keys()).cloned(), pos)
+            }
+            (Object::Int(l), Object::Int(r)) => Ok(Object::Int(l | r)),
+            (Object::Bool(l), Object::Bool(r)) => Ok(Object::Bool(l | r)),
+            _ => Err(Error::TypeError(
+                format!(
+                    "cannot calculate {} | {}",
+                    self.type_name(),
+                    other.type_name()
+                ),
+                pos,
+            )),
+        }
+    }
+
+    pub fn bit_xor(self, other: Self, pos: Position) -> Result<Object, Error> {
+        if let Some(result) = self.binary_special(&other, "xor", pos) {
+            return result;
+        }
+        match (&self, &other) {
+            (Object::Set(l, _), Object::Set(r, _)) => Object::new_set(
+                l.keys()
+                    .filter(|x| !r.contains_key(x))
+                    .chain(r.keys().filter(|x| !l.contains_key(x)))
+                    .cloned(),
+                pos,
+            ),
+            (Object::Int(l), Object::Int(r)) => Ok(Object::Int(l ^ r)),
+            (Object::Bool(l), Object::Bool(r)) => Ok(Object::Bool(l ^ r)),
+            _ => Err(Error::TypeError(
+                format!(
+                    "cannot calculate {} ^ {}",
+                    self.type_name(),
+                    other.type_name()
+                ),
+                pos,
+            )),
+        }
+    }
+
+    pub fn bit_not(self, pos: Position) -> Result<Object, Error> {
+        if let Some(result) = self.call_special("__invert__", vec![], pos) {
+            return result;
+        }
         match self {
-            Object::Int(v) => Ok(Object::Int(-v)),
-            Object::Float(v) => Ok(Object::Float(-v)),
+            Object::Int(v) => Ok(Object::Int(!v)),
             _ => Err(Error::TypeError(
-                format!("cannot make negative {}", self.type_name()),
+                format!("cannot calculate ~{}", self.type_name()),
                 pos,
             )),
         }
     }
 
+    pub fn shift_left(self, other: Self, pos: Position) -> Result<Object, Error> {
+        if let Some(result) = self.binary_special(&other, "lshift", pos) {
+            return result;
+        }
+        match (&self, &other) {
+            (Object::Int(l), Object::Int(r)) => {
+                let count = shift_count(*r, pos)?;
+                let shifted = l << count;
+                // bits shifted out must be the same as the sign bit
+                if shifted >> count != *l {
+                    return Err(overflow("<<", pos));
+                }
+                Ok(Object::Int(shifted))
+            }
+            _ => Err(Error::TypeError(
+                format!(
+                    "cannot calculate {} << {}",
+                    self.type_name(),
+                    other.type_name()
+                ),
+                pos,
+            )),
+        }
+    }
+
+    pub fn shift_right(self, other: Self, pos: Position) -> Result<Object, Error> {
+        if let Some(result) = self.binary_special(&other, "rshift", pos) {
+            return result;
+        }
+        match (&self, &other) {
+            (Object::Int(l), Object::Int(r)) => Ok(Object::Int(l >> shift_count(*r, pos)?)),
+            _ => Err(Error::TypeError(
+                format!(
+                    "cannot calculate {} >> {}",
+                    self.type_name(),
+                    other.type_name()
+                ),
+                pos,
+            )),
+        }
+    }
+
+    // numeric value as f64. only for int and float.
+    fn to_f64(&self) -> f64 {
+        match self {
+            Object::Int(i) => *i as f64,
+            Object::Float(f) => *f,
+            _ => unreachable!(),
+        }
+    }
+
+    // numeric value as complex, or None if this is not a number
+    pub fn to_complex(&self) -> Option<Complex> {
+        match self {
+            Object::Int(i) => Some(Complex::from(*i as f64)),
+            Object::Float(f) => Some(Complex::from(*f)),
+            Object::Complex(c) => Some(*c),
+            _ => None,
+        }
+    }
+
+    // operands of arithmetic in complex numbers, if one of them is complex and the other is
+    // a number
+    fn complex_operands(&self, other: &Object) -> Option<(Complex, Complex)> {
+        match (self, other) {
+            (Object::Complex(_), _) | (_, Object::Complex(_)) => {
+                Some((self.to_complex()?, other.to_complex()?))
+            }
+            _ => None,
+        }
+    }
+
     pub fn div(self, other: Self, pos: Position) -> Result<Object, Error> {
+        if let Some(result) = self.binary_special(&other, "div", pos) {
+            return result;
+        }
+        if let Some((l, r)) = self.complex_operands(&other) {
+            if r.is_zero() {
+                return Err(Error::DivideByZero("divide by zero".to_string(), pos));
+            }
+            return Ok(Object::Complex(l / r));
+        }
         match other {
             Object::Int(0i64) | Object::Float(0.0f64) => {
                 return Err(Error::DivideByZero("divide by zero".to_string(), pos))
@@ -302,7 +1351,9 @@ impl Object {
         }
         match self {
             Object::Int(l) => match other {
-                Object::Int(r) => Ok(Object::Int(l / r)),
+                Object::Int(r) => Ok(Object::Int(
+                    l.checked_div(r).ok_or_else(|| overflow("/", pos))?,
+                )),
                 Object::Float(r) => Ok(Object::Float(l as f64 / r)),
                 _ => Err(Error::TypeError(
                     format!("cannot divide int / {}", other.type_name()),
@@ -329,12 +1380,23 @@ impl Object {
     }
 
     pub fn pow(self, other: Self, pos: Position) -> Result<Object, Error> {
+        if let Some(result) = self.binary_special(&other, "pow", pos) {
+            return result;
+        }
+        if let Some((l, r)) = self.complex_operands(&other) {
+            return l.pow(r).map(Object::Complex).ok_or_else(|| {
+                Error::DivideByZero(
+                    "0 cannot be raised to a negative or complex power".to_string(),
+                    pos,
+                )
+            });
+        }
         match self {
             Object::Int(l) => match other {
-                Object::Int(r) => Ok(Object::Int(l.pow(r as u32))),
+                Object::Int(r) => int_pow(l, r, pos),
                 Object::Float(r) => Ok(Object::Float((l as f64).powf(r))),
                 _ => Err(Error::TypeError(
-                    format!("cannot multiply int * {}", other.type_name()),
+                    format!("cannot raise int ** {}", other.type_name()),
                     pos,
                 )),
             },
@@ -342,25 +1404,29 @@ impl Object {
                 Object::Int(r) => Ok(Object::Float(l.powf(r as f64))),
                 Object::Float(r) => Ok(Object::Float(l.powf(r))),
                 _ => Err(Error::TypeError(
-                    format!("cannot multiply float * {}", other.type_name()),
+                    format!("cannot raise float ** {}", other.type_name()),
                     pos,
                 )),
             },
             _ => Err(Error::TypeError(
-                format!(
-                    "cannot multiply {} * {}",
-                    self.type_name(),
-                    other.type_name()
-                ),
+                format!("cannot raise {} ** {}", self.type_name(), other.type_name()),
                 pos,
             )),
         }
     }
 
     pub fn mul(self, other: Self, pos: Position) -> Result<Object, Error> {
+        if let Some(result) = self.binary_special(&other, "mul", pos) {
+            return result;
+        }
+        if let Some((l, r)) = self.complex_operands(&other) {
+            return Ok(Object::Complex(l * r));
+        }
         match self {
             Object::Int(l) => match other {
-                Object::Int(r) => Ok(Object::Int(l * r)),
+                Object::Int(r) => Ok(Object::Int(
+                    l.checked_mul(r).ok_or_else(|| overflow("*", pos))?,
+                )),
                 Object::Float(r) => Ok(Object::Float(l as f64 * r)),
                 _ => Err(Error::TypeError(
                     format!("cannot multiply int * {}", other.type_name()),
@@ -387,9 +1453,27 @@ impl Object {
     }
 
     pub fn sub(self, other: Self, pos: Position) -> Result<Object, Error> {
+        if let Some(result) = self.binary_special(&other, "sub", pos) {
+            return result;
+        }
+        if let Some((l, r)) = self.complex_operands(&other) {
+            return Ok(Object::Complex(l - r));
+        }
         match self {
+            Object::Set(l, _) => match other {
+                // difference of sets
+                Object::Set(r, _) => {
+                    Object::new_set(l.keys().filter(|x| !r.contains_key(x)).cloned(), pos)
+                }
+                _ => Err(Error::TypeError(
+                    format!("cannot subtract set - {}", other.type_name()),
+                    pos,
+                )),
+            },
             Object::Int(l) => match other {
-                Object::Int(r) => Ok(Object::Int(l - r)),
+                Object::Int(r) => Ok(Object::Int(
+                    l.checked_sub(r).ok_or_else(|| overflow("-", pos))?,
+                )),
                 Object::Float(r) => Ok(Object::Float(l as f64 - r)),
                 _ => Err(Error::TypeError(
                     format!("cannot subtract int - {}", other.type_name()),
@@ -416,9 +1500,17 @@ impl Object {
     }
 
     pub fn add(self, other: Self, pos: Position) -> Result<Object, Error> {
+        if let Some(result) = self.binary_special(&other, "add", pos) {
+            return result;
+        }
+        if let Some((l, r)) = self.complex_operands(&other) {
+            return Ok(Object::Complex(l + r));
+        }
         match self {
             Object::Int(l) => match other {
-                Object::Int(r) => Ok(Object::Int(l + r)),
+                Object::Int(r) => Ok(Object::Int(
+                    l.checked_add(r).ok_or_else(|| overflow("+", pos))?,
+                )),
                 Object::Float(r) => Ok(Object::Float(l as f64 + r)),
                 _ => Err(Error::TypeError(
                     format!("cannot add int + {}", other.type_name()),
@@ -435,25 +1527,32 @@ impl Object {
             },
             Object::Str(l) => match other {
                 // concatenate str
-                Object::Str(r) => Ok(Object::Str(format!("{}{}", l, r))),
+                Object::Str(r) => Ok(Object::Str(format!("{}{}", l, r).into())),
                 _ => Err(Error::TypeError(
                     format!("cannot add str + {}", other.type_name()),
                     pos,
                 )),
             },
-            Object::List(l) => match other {
+            Object::List(l, _) => match other {
                 // concatenate str
-                Object::List(r) => {
-                    let mut out = vec![];
-                    out.append(&mut l.to_vec());
-                    out.append(&mut r.to_vec());
-                    Ok(Object::List(out))
-                }
+                Object::List(r, _) => Ok(Object::new_list(
+                    l.iter().chain(r.iter()).cloned().collect(),
+                )),
                 _ => Err(Error::TypeError(
                     format!("cannot add list + {}", other.type_name()),
                     pos,
                 )),
             },
+            Object::Tuple(mut l) => match other {
+                Object::Tuple(r) => {
+                    Rc::make_mut(&mut l).extend(r.iter().cloned());
+                    Ok(Object::Tuple(l))
+                }
+                _ => Err(Error::TypeError(
+                    format!("cannot add tuple + {}", other.type_name()),
+                    pos,
+                )),
+            },
             _ => Err(Error::TypeError(
                 format!("cannot add {} and {}", self.type_name(), other.type_name()),
                 pos,
@@ -461,3 +1560,107 @@ impl Object {
         }
     }
 }
+
+// indices of `xs[start..end]`. the range must be within the sequence, and an empty range
+// such as `3..1` results in an empty slice.
+fn slice_bounds(start: i64, end: i64, len: usize, pos: Position) -> Result<(usize, usize), Error> {
+    if start < 0 || end < 0 || start as usize > len || end as usize > len {
+        return Err(Error::IndexOutOfRange(
+            format!("range {}..{} is out of range of length {}", start, end, len),
+            pos,
+        ));
+    }
+    Ok((start as usize, (end as usize).max(start as usize)))
+}
+
+// shift count must be in the range of bit width of int
+fn shift_count(count: i64, pos: Position) -> Result<u32, Error> {
+    if (0..64).contains(&count) {
+        Ok(count as u32)
+    } else {
+        Err(Error::ValueError(
+            format!("shift count {} is out of range 0..64", count),
+            pos,
+        ))
+    }
+}
+
+fn overflow(operator: &str, pos: Position) -> Error {
+    Error::OverflowError(format!("integer overflow in `{}` operation", operator), pos)
+}
+
+// integer power. negative exponent results in float.
+fn int_pow(base: i64, exponent: i64, pos: Position) -> Result<Object, Error> {
+    if exponent < 0 {
+        if base == 0 {
+            return Err(Error::DivideByZero(
+                "0 cannot be raised to a negative power".to_string(),
+                pos,
+            ));
+        }
+        return Ok(Object::Float((base as f64).powf(exponent as f64)));
+    }
+    let result = match base {
+        0 | 1 => Some(if exponent == 0 { 1 } else { base }),
+        -1 => Some(if exponent % 2 == 0 { 1 } else { -1 }),
+        _ => u32::try_from(exponent)
+            .ok()
+            .and_then(|exponent| base.checked_pow(exponent)),
+    };
+    result.map(Object::Int).ok_or_else(|| overflow("**", pos))
+}
+
+// create an instance of the struct from positional arguments (in the order of fields) and
+// keyword arguments (by field name)
+pub fn construct(
+    def: Rc<StructDef>,
+    args: Vec<Object>,
+    kwargs: BTreeMap<String, Object>,
+    pos: Position,
+) -> Result<Object, Error> {
+    if args.len() > def.fields.len() {
+        return Err(Error::ArgumentError(
+            format!(
+                "struct `{}` has {} fields, but {} positional arguments were given.",
+                def.name,
+                def.fields.len(),
+                args.len()
+            ),
+            pos,
+        ));
+    }
+    let mut fields: Vec<Option<Object>> = args.into_iter().map(Some).collect();
+    fields.resize(def.fields.len(), None);
+    for (key, value) in kwargs {
+        match def.fields.iter().position(|field| *field == key) {
+            Some(i) if fields[i].is_none() => fields[i] = Some(value),
+            Some(_) => {
+                return Err(Error::ArgumentError(
+                    format!("field `{}` is given twice.", key),
+                    pos,
+                ))
+            }
+            None => {
+                return Err(Error::ArgumentError(
+                    format!("struct `{}` has no field `{}`.", def.name, key),
+                    pos,
+                ))
+            }
+        }
+    }
+    let mut values = vec![];
+    for (name, value) in def.fields.iter().zip(fields) {
+        match value {
+            Some(value) => values.push(value),
+            None => {
+                return Err(Error::ArgumentError(
+                    format!("field `{}` of struct `{}` is not given.", name, def.name),
+                    pos,
+                ))
+            }
+        }
+    }
+    let fields = Rc::new(RefCell::new(values));
+    gc::track(Handle::Fields(Rc::downgrade(&fields)));
+    Ok(Object::Instance { def, fields })
+}
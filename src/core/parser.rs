@@ -1,6 +1,8 @@
 use peg;
 
+use crate::core::string::Str;
 use std::collections::HashMap;
+use std::rc::Rc;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Position {
@@ -19,11 +21,21 @@ pub enum Node {
     // Literals
     IntegerLiteral(i64, Position),
     FloatLiteral(f64, Position),
-    StringLiteral(String, Position),
+    // imaginary part of complex number such as `2j`
+    ImaginaryLiteral(f64, Position),
+    StringLiteral(Str, Position),
     Nil(Position),
     Bool(bool, Position),
     List(Vec<Node>, Position),
     Hash(Vec<(Node, Node)>, Position),
+    // `(a, b)` or `(a,)`
+    Tuple(Vec<Node>, Position),
+    // `{a, b}` or `{a,}`
+    Set(Vec<Node>, Position),
+    // `[element for x in xs if cond]`
+    ListComprehension(Box<Node>, Vec<Comprehension>, Position),
+    // `{key -> value for k, v in h}`
+    HashComprehension(Box<Node>, Box<Node>, Vec<Comprehension>, Position),
 
     // Operators
     Neg(Box<Node>, Position),
@@ -32,6 +44,16 @@ pub enum Node {
     Mul(Box<Node>, Box<Node>, Position),
     Div(Box<Node>, Box<Node>, Position),
     Pow(Box<Node>, Box<Node>, Position),
+    FloorDiv(Box<Node>, Box<Node>, Position),
+    Mod(Box<Node>, Box<Node>, Position),
+
+    // Bitwise Operators
+    BitNot(Box<Node>, Position),
+    BitAnd(Box<Node>, Box<Node>, Position),
+    BitOr(Box<Node>, Box<Node>, Position),
+    BitXor(Box<Node>, Box<Node>, Position),
+    ShiftLeft(Box<Node>, Box<Node>, Position),
+    ShiftRight(Box<Node>, Box<Node>, Position),
 
     // Compare Operator
     CmpLessThan(Box<Node>, Box<Node>, Position),
@@ -40,12 +62,23 @@ pub enum Node {
     CmpGreaterThanEq(Box<Node>, Box<Node>, Position),
     CmpEq(Box<Node>, Box<Node>, Position),
     CmpNotEq(Box<Node>, Box<Node>, Position),
+    CmpIn(Box<Node>, Box<Node>, Position),
+    CmpNotIn(Box<Node>, Box<Node>, Position),
+    CmpIs(Box<Node>, Box<Node>, Position),
+    CmpIsNot(Box<Node>, Box<Node>, Position),
+    // chained comparison such as `a < b <= c`
+    CmpChain(Box<Node>, Vec<(CompareOperator, Node)>, Position),
 
     // Logical Operators
     LogicalNot(Box<Node>, Position),
     LogicalOr(Box<Node>, Box<Node>, Position),
     LogicalAnd(Box<Node>, Box<Node>, Position),
 
+    // `a..b`, `a..=b` (inclusive)
+    Range(Box<Node>, Box<Node>, bool, Position),
+    // `a ?? b`
+    NilCoalesce(Box<Node>, Box<Node>, Position),
+
     // Identifier
     Identifier(String, Position),
 
@@ -68,17 +101,234 @@ pub enum Node {
         position: Position,
     },
 
+    // `a.b`, which can be resolved to a method
     GetAttribute(Box<Node>, Box<Node>, Position),
+    // `a?.b`
+    OptionalGetAttribute(Box<Node>, Box<Node>, Position),
+    // `a[i]`
+    Index(Box<Node>, Box<Node>, Position),
+    // `a?[i]`
+    OptionalIndex(Box<Node>, Box<Node>, Position),
 
     Assign(Vec<Node>, Vec<Node>, Position),
+    CompoundAssign(Box<Node>, AssignOperator, Box<Node>, Position),
     IfElse(Box<Node>, Box<Node>, Box<Node>, Position),
     Return(Box<Node>, Position),
     Drop(Vec<String>, Position),
     Loop(Vec<Node>, Position),
+    // `for a, b in iterable { ... }`
+    For(Vec<String>, Box<Node>, Vec<Node>, Position),
+    // `yield value` or `name = yield value`, which are allowed only as statements. the
+    // variable receives the value sent when the generator is resumed. `await` is the same.
+    Yield(Option<String>, Box<Node>, Position),
+    // body of a function which contains `yield`. evaluating it creates a generator.
+    Generator(Rc<Node>, Position),
+    Match(Box<Node>, Vec<MatchArm>, Position),
+    // `enum Name { Variant(field), UnitVariant, method(args) -> body }`. unit variants have
+    // no field list.
+    Enum {
+        name: String,
+        variants: Vec<(String, Option<Vec<String>>)>,
+        methods: Vec<(String, Node)>,
+        position: Position,
+    },
+    // `struct Name { field, method(args) -> body }`. methods are `Node::Function`.
+    Struct {
+        name: String,
+        fields: Vec<String>,
+        methods: Vec<(String, Node)>,
+        position: Position,
+    },
+    FormatString(Vec<FormatPart>, Position),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOperator {
+    Eq,
+    NotEq,
+    LessThan,
+    LessThanEq,
+    GreaterThan,
+    GreaterThanEq,
+    In,
+    NotIn,
+    Is,
+    IsNot,
+}
+
+// `a < b` is parsed as a binary node, `a < b < c` as `CmpChain`
+fn build_comparison(first: Node, rest: Vec<(CompareOperator, Node)>, position: Position) -> Node {
+    if rest.len() > 1 {
+        return Node::CmpChain(Box::new(first), rest, position);
+    }
+    let (operator, right) = rest.into_iter().next().unwrap();
+    let (left, right) = (Box::new(first), Box::new(right));
+    match operator {
+        CompareOperator::Eq => Node::CmpEq(left, right, position),
+        CompareOperator::NotEq => Node::CmpNotEq(left, right, position),
+        CompareOperator::LessThan => Node::CmpLessThan(left, right, position),
+        CompareOperator::LessThanEq => Node::CmpLessThanEq(left, right, position),
+        CompareOperator::GreaterThan => Node::CmpGreaterThan(left, right, position),
+        CompareOperator::GreaterThanEq => Node::CmpGreaterThanEq(left, right, position),
+        CompareOperator::In => Node::CmpIn(left, right, position),
+        CompareOperator::NotIn => Node::CmpNotIn(left, right, position),
+        CompareOperator::Is => Node::CmpIs(left, right, position),
+        CompareOperator::IsNot => Node::CmpIsNot(left, right, position),
+    }
+}
+
+fn build_pipe(value: Node, function: Node, position: Position) -> Node {
+    match function {
+        Node::CallFunction {
+            callable,
+            mut arguments,
+            keyword_arguments,
+            position: _,
+        } => {
+            arguments.insert(0, value);
+            Node::CallFunction {
+                callable,
+                arguments,
+                keyword_arguments,
+                position,
+            }
+        }
+        _ => Node::CallFunction {
+            callable: Box::new(function),
+            arguments: vec![value],
+            keyword_arguments: HashMap::new(),
+            position,
+        },
+    }
+}
+
+// whether the statement contains `yield`, not including nested functions. `yield` can be
+// placed only at statement positions, so expressions are not searched.
+pub fn contains_yield(node: &Node) -> bool {
+    match node {
+        Node::Yield(_, _, _) => true,
+        Node::Sequence(nodes, _) | Node::Loop(nodes, _) | Node::For(_, _, nodes, _) => {
+            nodes.iter().any(contains_yield)
+        }
+        Node::IfElse(_, a, b, _) => contains_yield(a) || contains_yield(b),
+        Node::Match(_, arms, _) => arms.iter().any(|arm| contains_yield(&arm.body)),
+        _ => false,
+    }
+}
+
+// body of a function, which is a generator if it contains `yield`
+fn function_body(body: Node, position: Position) -> Node {
+    if contains_yield(&body) {
+        Node::Generator(Rc::new(body), position)
+    } else {
+        body
+    }
+}
+
+// member of `enum` definition
+#[derive(Clone)]
+enum EnumMember {
+    Variant(String, Option<Vec<String>>),
+    Method(String, Node),
 }
 
-const RESERVED_WORDS: [&str; 11] = [
-    "if", "then", "else", "not", "and", "or", "return", "nil", "true", "false", "drop",
+fn identifier_name(node: Node) -> String {
+    match node {
+        Node::Identifier(name, _) => name,
+        _ => panic!("parse error"),
+    }
+}
+
+// operator of compound assignment such as `+=`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AssignOperator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Mod,
+    FloorDiv,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+}
+
+// part of interpolated string literal `f"..."`
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatPart {
+    Literal(String),
+    // embedded expression and its format spec
+    Expression(Node, String),
+}
+
+// pattern of `match` expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Wildcard,
+    // literal value, compared with `==`
+    Literal(Node),
+    // `lower..upper` or `lower..=upper`
+    Range(Node, Node, bool),
+    // type name such as `int`, `str`
+    Type(String),
+    // bind the value to a variable, optionally constrained by `name: type`
+    Binding(String, Option<Box<Pattern>>),
+    // `[first, second, ..rest]`
+    List(Vec<Pattern>),
+    // `..rest` or `..` in list pattern
+    Rest(Option<String>),
+    // `{"key" -> pattern, ...}`
+    Hash(Vec<(Node, Pattern)>),
+    // `pattern | pattern`
+    Or(Vec<Pattern>),
+    // variant of enum or struct such as `Circle(r)`, `Shape.Empty` and `Point(x, y)`.
+    // fields are not checked if they are omitted.
+    Variant {
+        enum_name: Option<String>,
+        name: String,
+        fields: Option<Vec<Pattern>>,
+    },
+}
+
+// clause of comprehensions. the first clause is always `For`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Comprehension {
+    For(Vec<String>, Node),
+    If(Node),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Node>,
+    pub body: Node,
+}
+
+// parse integer literal with `_` separators
+fn parse_integer(
+    sign: &str,
+    digits: &str,
+    radix: u32,
+    position: Position,
+) -> Result<Node, &'static str> {
+    let value = i64::from_str_radix(&format!("{}{}", sign, digits.replace('_', "")), radix);
+    if let Ok(value) = value {
+        Ok(Node::IntegerLiteral(value, position))
+    } else {
+        Err("failed to parse integer")
+    }
+}
+
+const TYPE_NAMES: [&str; 11] = [
+    "bool", "int", "float", "complex", "str", "list", "hash", "tuple", "set", "range", "function",
+];
+
+const RESERVED_WORDS: [&str; 20] = [
+    "if", "then", "else", "not", "and", "or", "return", "nil", "true", "false", "drop", "match",
+    "in", "is", "struct", "self", "enum", "for", "yield", "await",
 ];
 
 peg::parser! {
@@ -102,6 +352,16 @@ peg::parser! {
         rule operator_mul() = "*"
         rule operator_div() = "/"
         rule operator_pow() = "**"
+        rule operator_floor_div() = "//"
+        rule operator_mod() = "%"
+
+        rule operator_bit_and() = "&"
+        rule operator_bit_or() = "|" !">"
+        rule operator_pipe() = "|>"
+        rule operator_bit_xor() = "^"
+        rule operator_bit_not() = "~"
+        rule operator_shift_left() = "<<"
+        rule operator_shift_right() = ">>"
 
         rule operator_cmp_eq() = "=="
         rule operator_cmp_lt() = "<"
@@ -112,6 +372,9 @@ peg::parser! {
 
         rule comma() = ","
         rule period() = "."
+        rule optional_period() = "?."
+        rule optional_left_bracket() = "?["
+        rule operator_nil_coalesce() = "??"
 
         rule equal() = "="
         rule right_arrow() = "->"
@@ -128,24 +391,41 @@ peg::parser! {
         rule keyword_false() = "false"
         rule keyword_loop() = "loop"
         rule keyword_drop() = "drop"
+        rule keyword_match() = "match" !identifier_char()
+        rule keyword_in() = "in" !identifier_char()
+        rule keyword_is() = "is" !identifier_char()
+        rule keyword_struct() = "struct" !identifier_char()
+        rule keyword_enum() = "enum" !identifier_char()
+        rule keyword_for() = "for" !identifier_char()
+        rule keyword_yield() = "yield" !identifier_char()
+        rule keyword_await() = "await" !identifier_char()
+        rule keyword_self() = "self" !identifier_char()
+
+        rule identifier_char() = ['a'..='z' | 'A'..='Z' | '_' | '0'..='9']
 
         // Literals
         #[cache_left_rec]
         rule integer_literal() -> Node
-            = begin:position!() sign:$("-"?) value:$(['0'..='9']+) end:position!() {?
-                let value = format!("{}{}", sign, value).parse::<i64>();
-                if value.is_ok() {
-                    Ok(Node::IntegerLiteral(value.unwrap(), Position::new(begin, end)))
-                }
-                else {
-                    Err("failed to parse integer")
-                }
+            = begin:position!() sign:$("-"?) "0x" value:$((['0'..='9' | 'a'..='f' | 'A'..='F']+) ++ "_") end:position!() {?
+                parse_integer(sign, value, 16, Position::new(begin, end))
+            }
+            / begin:position!() sign:$("-"?) "0o" value:$((['0'..='7']+) ++ "_") end:position!() {?
+                parse_integer(sign, value, 8, Position::new(begin, end))
+            }
+            / begin:position!() sign:$("-"?) "0b" value:$((['0' | '1']+) ++ "_") end:position!() {?
+                parse_integer(sign, value, 2, Position::new(begin, end))
+            }
+            / begin:position!() sign:$("-"?) value:$((['0'..='9']+) ++ "_") end:position!() {?
+                parse_integer(sign, value, 10, Position::new(begin, end))
             }
 
+        rule decimal_digits() = (['0'..='9']+) ++ "_"
+        rule exponent() = ['e' | 'E'] ['+' | '-']? decimal_digits()
+
         #[cache_left_rec]
         rule float_literal() -> Node
-            = begin:position!() sign:$("-"?) value_1:$(['0'..='9']+) period() value_2:$(['0'..='9']+) end:position!() {?
-                let value = format!("{}{}.{}", sign, value_1, value_2).parse::<f64>();
+            = begin:position!() value:$("-"? (decimal_digits() period() decimal_digits() exponent()? / decimal_digits() exponent() / period() decimal_digits() exponent()?)) end:position!() {?
+                let value = value.replace('_', "").parse::<f64>();
                 if value.is_ok() {
                     Ok(Node::FloatLiteral(value.unwrap(), Position::new(begin, end)))
                 }
@@ -154,6 +434,14 @@ peg::parser! {
                 }
             }
 
+        rule imaginary_literal() -> Node
+            = begin:position!() value:$("-"? (decimal_digits() period() decimal_digits() exponent()? / decimal_digits() exponent()? / period() decimal_digits() exponent()?)) "j" !identifier_char() end:position!() {?
+                match value.replace('_', "").parse::<f64>() {
+                    Ok(value) => Ok(Node::ImaginaryLiteral(value, Position::new(begin, end))),
+                    Err(_) => Err("failed to parse imaginary number"),
+                }
+            }
+
         // Nil
         #[cache_left_rec]
         rule nil_literal() -> Node
@@ -195,20 +483,37 @@ peg::parser! {
             }
 
         #[cache_left_rec]
-        rule escaped_char() -> char
+        rule escape_sequence() -> char
             = "\\\\" {
                 '\\'
             }
             / "\\\"" {
                 '\"'
             }
+            / "\\'" {
+                '\''
+            }
             / "\\n" {
                 '\n'
             }
-            / "\\" c:[^'\"'] {
-                c
+            / "\\t" {
+                '\t'
+            }
+            / "\\r" {
+                '\r'
+            }
+            / "\\0" {
+                '\0'
+            }
+            / "\\u{" code:$(['0'..='9' | 'a'..='f' | 'A'..='F']*<1,6>) "}" {?
+                char::from_u32(u32::from_str_radix(code, 16).unwrap()).ok_or("invalid unicode escape")
             }
-            / c:[^'\"'] {
+
+        #[cache_left_rec]
+        rule escaped_char() -> char
+            = escape_sequence()
+            // a backslash which does not begin a valid escape sequence is an error
+            / c:[^'\"' | '\\'] {
                 c
             }
 
@@ -220,8 +525,41 @@ peg::parser! {
 
         #[cache_left_rec]
         rule string_literal() -> Node
-            = begin:position!() "\"" s:parse_string() "\"" end:position!() {
-                Node::StringLiteral(s, Position::new(begin, end))
+            = begin:position!() "f\"" parts:format_string_part()* "\"" end:position!() {
+                Node::FormatString(parts, Position::new(begin, end))
+            }
+            // raw string: backslashes are not escaped
+            / begin:position!() "r\"" s:$([^'\"']*) "\"" end:position!() {
+                Node::StringLiteral(Str::from(s), Position::new(begin, end))
+            }
+            / begin:position!() "r'" s:$([^'\'']*) "'" end:position!() {
+                Node::StringLiteral(Str::from(s), Position::new(begin, end))
+            }
+            // multi-line string
+            / begin:position!() "\"\"\"" chars:(!"\"\"\"" c:(escape_sequence() / [^'\\']) { c })* "\"\"\"" end:position!() {
+                Node::StringLiteral(Str::from(chars.iter().collect::<String>()), Position::new(begin, end))
+            }
+            / begin:position!() "\"" s:parse_string() "\"" end:position!() {
+                Node::StringLiteral(Str::from(s), Position::new(begin, end))
+            }
+            / begin:position!() "'" chars:(escape_sequence() / [^'\'' | '\\'])* "'" end:position!() {
+                Node::StringLiteral(Str::from(chars.iter().collect::<String>()), Position::new(begin, end))
+            }
+
+        // `{{` and `}}` are escaped braces, `{expression:spec}` is replaced with formatted value.
+        #[cache_left_rec]
+        rule format_string_part() -> FormatPart
+            = "{{" {
+                FormatPart::Literal("{".to_string())
+            }
+            / "}}" {
+                FormatPart::Literal("}".to_string())
+            }
+            / left_brace() _ e:expression() _ spec:(":" s:$([^'}' | '\"']*) { s.to_string() })? right_brace() {
+                FormatPart::Expression(e, spec.unwrap_or_default())
+            }
+            / chars:(!['{' | '}'] c:escaped_char() { c })+ {
+                FormatPart::Literal(chars.iter().collect())
             }
 
         // Statements
@@ -233,6 +571,12 @@ peg::parser! {
             / _ begin:position!() keyword_loop() newline()? _ left_brace() _ newline()? _ seq:(sequence() ** newline()) _ newline()? _ right_brace() end:position!() {
                 Node::Loop(seq, Position::new(begin, end))
             }
+            / _ begin:position!() keyword_for() _ names:(identifier() ++ (_ comma() _)) _ keyword_in() _ iterable:expression() _ left_brace() _ newline()? _ seq:(sequence() ** newline()) _ newline()? _ right_brace() end:position!() {
+                Node::For(names.into_iter().map(identifier_name).collect(), Box::new(iterable), seq, Position::new(begin, end))
+            }
+            / match_expression()
+            / struct_definition()
+            / enum_definition()
             / _ begin:position!() keyword_if() _ condition:sequence() newline()? _ keyword_then()? newline()? _ expr_true:sequence() newline()? _ keyword_else() _ newline()? _ expr_false:sequence() end:position!() _ {
                 Node::IfElse(Box::new(condition), Box::new(expr_true), Box::new(expr_false), Position::new(begin, end))
             }
@@ -241,12 +585,211 @@ peg::parser! {
             }
             / statement()
 
+        // Struct
+        #[cache_left_rec]
+        rule struct_definition() -> Node
+            = _ begin:position!() keyword_struct() _ name:identifier() _ left_brace() _ newline()? _ members:(struct_member() ** struct_member_separator()) _ struct_member_separator()? _ right_brace() end:position!() _ {?
+                let name = match name {
+                    Node::Identifier(name, _) => name,
+                    _ => return Err("struct name"),
+                };
+                let mut fields = vec![];
+                let mut methods: Vec<(String, Node)> = vec![];
+                for (member, method) in members {
+                    if fields.contains(&member) || methods.iter().any(|(m, _)| *m == member) {
+                        return Err("struct members must have different names");
+                    }
+                    match method {
+                        Some(method) => methods.push((member, method)),
+                        None => fields.push(member),
+                    }
+                }
+                Ok(Node::Struct { name, fields, methods, position: Position::new(begin, end) })
+            }
+
+        rule struct_member_separator() = _ comma() _ newline()? / newline()
+
+        // field name, or method name and `Node::Function`
+        #[cache_left_rec]
+        rule struct_member() -> (String, Option<Node>)
+            = _ begin:position!() name:identifier() _ left_paren() _ args:arguments_signature() _ right_paren() _ right_arrow() _ seq:expression() end:position!() {?
+                let (args, kwargs) = args;
+                match name {
+                    Node::Identifier(name, _) => Ok((name, Some(Node::Function {
+                        arguments: args,
+                        keyword_arguments: kwargs,
+                        sequence: Box::new(function_body(seq, Position::new(begin, end))),
+                        position: Position::new(begin, end)
+                    }))),
+                    _ => Err("method name"),
+                }
+            }
+            / _ name:identifier() {?
+                match name {
+                    Node::Identifier(name, _) => Ok((name, None)),
+                    _ => Err("field name"),
+                }
+            }
+
+        // Enum
+        #[cache_left_rec]
+        rule enum_definition() -> Node
+            = _ begin:position!() keyword_enum() _ name:identifier() _ left_brace() _ newline()? _ members:(enum_member() ** struct_member_separator()) _ struct_member_separator()? _ right_brace() end:position!() _ {?
+                let mut variants: Vec<(String, Option<Vec<String>>)> = vec![];
+                let mut methods: Vec<(String, Node)> = vec![];
+                for member in members {
+                    let member_name = match &member {
+                        EnumMember::Variant(name, _) | EnumMember::Method(name, _) => name.clone(),
+                    };
+                    if variants.iter().any(|(v, _)| *v == member_name) || methods.iter().any(|(m, _)| *m == member_name) {
+                        return Err("enum members must have different names");
+                    }
+                    match member {
+                        EnumMember::Variant(name, fields) => variants.push((name, fields)),
+                        EnumMember::Method(name, method) => methods.push((name, method)),
+                    }
+                }
+                Ok(Node::Enum { name: identifier_name(name), variants, methods, position: Position::new(begin, end) })
+            }
+
+        #[cache_left_rec]
+        rule enum_member() -> EnumMember
+            = _ name:identifier() _ left_paren() _ fields:(identifier() ** (_ comma() _)) _ right_paren() !(_ right_arrow()) {
+                EnumMember::Variant(identifier_name(name), Some(fields.into_iter().map(identifier_name).collect()))
+            }
+            / member:struct_member() {
+                match member {
+                    (name, Some(method)) => EnumMember::Method(name, method),
+                    (name, None) => EnumMember::Variant(name, None),
+                }
+            }
+
+        // Match
+        #[cache_left_rec]
+        rule match_expression() -> Node
+            = _ begin:position!() keyword_match() _ value:expression() _ left_brace() _ newline()? _ arms:(match_arm() ** match_arm_separator()) _ match_arm_separator()? _ right_brace() end:position!() _ {
+                Node::Match(Box::new(value), arms, Position::new(begin, end))
+            }
+
+        rule match_arm_separator() = _ comma() _ newline()? / newline()
+
+        #[cache_left_rec]
+        rule match_arm() -> MatchArm
+            = _ pattern:pattern() _ guard:(keyword_if() _ e:expression() _ { e })? right_arrow() _ body:(yield_statement() / expression()) {
+                MatchArm { pattern, guard, body }
+            }
+
+        #[cache_left_rec]
+        rule pattern() -> Pattern
+            = patterns:(pattern_single() ++ (_ "|" _)) {
+                if patterns.len() == 1 {
+                    patterns.into_iter().next().unwrap()
+                }
+                else {
+                    Pattern::Or(patterns)
+                }
+            }
+
+        #[cache_left_rec]
+        rule pattern_literal() -> Node
+            = float_literal()
+            / integer_literal()
+            / string_literal()
+            / nil_literal()
+            / bool_literal()
+
+        #[cache_left_rec]
+        rule pattern_single() -> Pattern
+            = lower:pattern_literal() _ "..=" _ upper:pattern_literal() {
+                Pattern::Range(lower, upper, true)
+            }
+            / lower:pattern_literal() _ ".." _ upper:pattern_literal() {
+                Pattern::Range(lower, upper, false)
+            }
+            / literal:pattern_literal() {
+                Pattern::Literal(literal)
+            }
+            / "_" !identifier_char() {
+                Pattern::Wildcard
+            }
+            / ".." name:identifier()? {
+                Pattern::Rest(name.map(|n| if let Node::Identifier(n, _) = n { n } else { panic!("parse error") }))
+            }
+            / left_bracket() _ newline()? _ elements:(pattern() ** (_ comma() _ newline()? _)) _ comma()? _ newline()? _ right_bracket() {?
+                if elements.iter().filter(|p| matches!(p, Pattern::Rest(_))).count() > 1 {
+                    Err("list pattern can contain only one `..`")
+                }
+                else {
+                    Ok(Pattern::List(elements))
+                }
+            }
+            / left_brace() _ newline()? _ elements:(pattern_hash_element() ** (_ comma() _ newline()? _)) _ comma()? _ newline()? _ right_brace() {
+                Pattern::Hash(elements)
+            }
+            / enum_name:identifier() _ period() _ name:identifier() fields:pattern_variant_fields()? {
+                Pattern::Variant { enum_name: Some(identifier_name(enum_name)), name: identifier_name(name), fields }
+            }
+            / name:identifier() fields:pattern_variant_fields() {
+                Pattern::Variant { enum_name: None, name: identifier_name(name), fields: Some(fields) }
+            }
+            / name:identifier() _ ":" _ type_name:identifier() {?
+                match (name, type_name) {
+                    // user-defined type names start with an uppercase letter
+                    (Node::Identifier(name, _), Node::Identifier(type_name, _)) if TYPE_NAMES.contains(&type_name.as_str()) || type_name.starts_with(|c: char| c.is_ascii_uppercase()) => {
+                        Ok(Pattern::Binding(name, Some(Box::new(Pattern::Type(type_name)))))
+                    }
+                    _ => Err("unknown type name")
+                }
+            }
+            / name:identifier() {
+                if let Node::Identifier(name, _) = name {
+                    if TYPE_NAMES.contains(&name.as_str()) {
+                        Pattern::Type(name)
+                    }
+                    // an uppercase name is a variant such as `Empty`, not a binding
+                    else if name.starts_with(|c: char| c.is_ascii_uppercase()) {
+                        Pattern::Variant { enum_name: None, name, fields: None }
+                    }
+                    else {
+                        Pattern::Binding(name, None)
+                    }
+                }
+                else {
+                    panic!("parse error")
+                }
+            }
+
+        rule pattern_variant_fields() -> Vec<Pattern>
+            = _ left_paren() _ fields:(pattern() ** (_ comma() _)) _ comma()? _ right_paren() {
+                fields
+            }
+
+        #[cache_left_rec]
+        rule pattern_hash_element() -> (Node, Pattern)
+            = key:pattern_literal() _ right_arrow() _ value:pattern() {
+                (key, value)
+            }
+
         #[cache_left_rec]
         rule statement() -> Node
-            = _ e:expression() _ {
+            = yield_statement()
+            / _ e:expression() _ {
                 e
             }
 
+        rule yield_statement() -> Node
+            = _ begin:position!() target:yield_target()? (keyword_yield() / keyword_await()) _ expr:expression() end:position!() _ {
+                Node::Yield(target, Box::new(expr), Position::new(begin, end))
+            }
+            / _ begin:position!() target:yield_target()? (keyword_yield() / keyword_await()) end:position!() _ {
+                Node::Yield(target, Box::new(Node::Nil(Position::new(begin, end))), Position::new(begin, end))
+            }
+
+        rule yield_target() -> String
+            = name:identifier() _ equal() _ {
+                identifier_name(name)
+            }
+
         #[cache_left_rec]
         rule expression() -> Node
             = return_or_drop()
@@ -276,7 +819,7 @@ peg::parser! {
         // variable name for assign
         #[cache_left_rec]
         rule assign_left_elem() -> Node
-            = get_attr()
+            = call_function()
             / identifier()
 
        // Assign
@@ -289,9 +832,26 @@ peg::parser! {
             = expression() ++ (_ comma() _)
 
 
+        rule assign_operator() -> AssignOperator
+            = "**=" { AssignOperator::Pow }
+            / "//=" { AssignOperator::FloorDiv }
+            / "<<=" { AssignOperator::ShiftLeft }
+            / ">>=" { AssignOperator::ShiftRight }
+            / "+=" { AssignOperator::Add }
+            / "-=" { AssignOperator::Sub }
+            / "*=" { AssignOperator::Mul }
+            / "/=" { AssignOperator::Div }
+            / "%=" { AssignOperator::Mod }
+            / "&=" { AssignOperator::BitAnd }
+            / "|=" { AssignOperator::BitOr }
+            / "^=" { AssignOperator::BitXor }
+
         #[cache_left_rec]
         rule assign() -> Node
-            = begin:position!() left:assign_left() _ equal() _ right:assign_right() end:position!() {?
+            = begin:position!() left:assign_left_elem() _ operator:assign_operator() _ right:expression() end:position!() {
+                Node::CompoundAssign(Box::new(left), operator, Box::new(right), Position::new(begin, end))
+            }
+            / begin:position!() left:assign_left() _ equal() _ right:assign_right() end:position!() {?
                 if left.len() == right.len() {
                     Ok(Node::Assign(left, right, Position::new(begin, end)))
                 }
@@ -299,6 +859,21 @@ peg::parser! {
                     Err("The number on the right side and the left side must be the same.")
                 }
             }
+            / pipe()
+
+        // `x |> f` is `f(x)`, and `x |> f(a)` is `f(x, a)`
+        #[cache_left_rec]
+        rule pipe() -> Node
+            = begin:position!() left:pipe() _ operator_pipe() _ right:nil_coalesce() end:position!() {
+                build_pipe(left, right, Position::new(begin, end))
+            }
+            / nil_coalesce()
+
+        #[cache_left_rec]
+        rule nil_coalesce() -> Node
+            = begin:position!() left:nil_coalesce() _ operator_nil_coalesce() _ right:logical_or() end:position!() {
+                Node::NilCoalesce(Box::new(left), Box::new(right), Position::new(begin, end))
+            }
             / logical_or()
 
         #[cache_left_rec]
@@ -325,23 +900,63 @@ peg::parser! {
         // Compare
         #[cache_left_rec]
         rule compare() -> Node
-            = begin:position!() left:compare() _ operator_cmp_eq() _ right:arithmetic_expression() end:position!() {
-                Node::CmpEq(Box::new(left), Box::new(right), Position::new(begin, end))
+            = begin:position!() first:range() rest:(_ operator:compare_operator() _ right:range() { (operator, right) })+ end:position!() {
+                build_comparison(first, rest, Position::new(begin, end))
             }
-            / begin:position!() left:compare() _ operator_cmp_noteq() _ right:arithmetic_expression() end:position!() {
-                Node::CmpNotEq(Box::new(left), Box::new(right), Position::new(begin, end))
+            / range()
+
+        rule compare_operator() -> CompareOperator
+            = operator_cmp_eq() { CompareOperator::Eq }
+            / operator_cmp_noteq() { CompareOperator::NotEq }
+            / operator_cmp_lteq() { CompareOperator::LessThanEq }
+            / operator_cmp_gteq() { CompareOperator::GreaterThanEq }
+            / operator_cmp_lt() { CompareOperator::LessThan }
+            / operator_cmp_gt() { CompareOperator::GreaterThan }
+            / keyword_in() { CompareOperator::In }
+            / keyword_not() _ keyword_in() { CompareOperator::NotIn }
+            / keyword_is() _ keyword_not() !identifier_char() { CompareOperator::IsNot }
+            / keyword_is() { CompareOperator::Is }
+
+        // Range
+        #[cache_left_rec]
+        rule range() -> Node
+            = begin:position!() lower:bit_or() _ "..=" _ upper:bit_or() end:position!() {
+                Node::Range(Box::new(lower), Box::new(upper), true, Position::new(begin, end))
             }
-            / begin:position!() left:compare() _ operator_cmp_lt() _ right:arithmetic_expression() end:position!() {
-                Node::CmpLessThan(Box::new(left), Box::new(right), Position::new(begin, end))
+            / begin:position!() lower:bit_or() _ ".." _ upper:bit_or() end:position!() {
+                Node::Range(Box::new(lower), Box::new(upper), false, Position::new(begin, end))
+            }
+            / bit_or()
+
+        // Bitwise Operators
+        #[cache_left_rec]
+        rule bit_or() -> Node
+            = begin:position!() left:bit_or() _ operator_bit_or() _ right:bit_xor() end:position!() {
+                Node::BitOr(Box::new(left), Box::new(right), Position::new(begin, end))
+            }
+            / bit_xor()
+
+        #[cache_left_rec]
+        rule bit_xor() -> Node
+            = begin:position!() left:bit_xor() _ operator_bit_xor() _ right:bit_and() end:position!() {
+                Node::BitXor(Box::new(left), Box::new(right), Position::new(begin, end))
             }
-            / begin:position!() left:compare() _ operator_cmp_lteq() _ right:arithmetic_expression() end:position!() {
-                Node::CmpLessThanEq(Box::new(left), Box::new(right), Position::new(begin, end))
+            / bit_and()
+
+        #[cache_left_rec]
+        rule bit_and() -> Node
+            = begin:position!() left:bit_and() _ operator_bit_and() _ right:shift() end:position!() {
+                Node::BitAnd(Box::new(left), Box::new(right), Position::new(begin, end))
             }
-            / begin:position!() left:compare() _ operator_cmp_gt() _ right:arithmetic_expression() end:position!() {
-                Node::CmpGreaterThan(Box::new(left), Box::new(right), Position::new(begin, end))
+            / shift()
+
+        #[cache_left_rec]
+        rule shift() -> Node
+            = begin:position!() left:shift() _ operator_shift_left() _ right:arithmetic_expression() end:position!() {
+                Node::ShiftLeft(Box::new(left), Box::new(right), Position::new(begin, end))
             }
-            / begin:position!() left:compare() _ operator_cmp_gteq() _ right:arithmetic_expression() end:position!() {
-                Node::CmpGreaterThanEq(Box::new(left), Box::new(right), Position::new(begin, end))
+            / begin:position!() left:shift() _ operator_shift_right() _ right:arithmetic_expression() end:position!() {
+                Node::ShiftRight(Box::new(left), Box::new(right), Position::new(begin, end))
             }
             / arithmetic_expression()
 
@@ -361,9 +976,15 @@ peg::parser! {
             = begin:position!() left:term() _ operator_mul() _ right:number_with_pow() end:position!() {
                 Node::Mul(Box::new(left), Box::new(right), Position::new(begin, end))
             }
-            /begin:position!() left:term() _ operator_div() _ right:number_with_pow() end:position!() {
+            / begin:position!() left:term() _ operator_floor_div() _ right:number_with_pow() end:position!() {
+                Node::FloorDiv(Box::new(left), Box::new(right), Position::new(begin, end))
+            }
+            / begin:position!() left:term() _ operator_div() _ right:number_with_pow() end:position!() {
                 Node::Div(Box::new(left), Box::new(right), Position::new(begin, end))
             }
+            / begin:position!() left:term() _ operator_mod() _ right:number_with_pow() end:position!() {
+                Node::Mod(Box::new(left), Box::new(right), Position::new(begin, end))
+            }
             / number_with_pow()
 
         #[cache_left_rec]
@@ -374,6 +995,9 @@ peg::parser! {
             / begin:position!() operator_sub() _ value:call_function() end:position!() {
                 Node::Neg(Box::new(value), Position::new(begin, end))
             }
+            / begin:position!() operator_bit_not() _ value:call_function() end:position!() {
+                Node::BitNot(Box::new(value), Position::new(begin, end))
+            }
             / call_function()
 
 
@@ -419,11 +1043,8 @@ peg::parser! {
                     position: Position::new(begin, end)
                 }
             }
-            / get_attr()
-
-        #[cache_left_rec]
-        rule get_attr() -> Node
-            = begin:position!() reciever:get_attr() _ period() _ attribute_name:identifier() end:position!() {
+            // method calls and indexing can follow calls, such as `s.trim().upper()` and `f()[0]`
+            / begin:position!() reciever:call_function() _ period() _ attribute_name:identifier() end:position!() {
                 let mut attr_name: String;
                 if let Node::Identifier(name, _) = attribute_name {
                     attr_name = name;
@@ -431,16 +1052,50 @@ peg::parser! {
                 else {
                     panic!("parse error")
                 }
-                Node::GetAttribute(Box::new(reciever), Box::new(Node::StringLiteral(attr_name, Position::new(begin, end))), Position::new(begin, end))
+                Node::GetAttribute(Box::new(reciever), Box::new(Node::StringLiteral(Str::intern(&attr_name), Position::new(begin, end))), Position::new(begin, end))
+            }
+            / begin:position!() reciever:call_function() _ optional_period() _ attribute_name:identifier() end:position!() {?
+                match attribute_name {
+                    Node::Identifier(name, _) => Ok(Node::OptionalGetAttribute(Box::new(reciever), Box::new(Node::StringLiteral(Str::intern(&name), Position::new(begin, end))), Position::new(begin, end))),
+                    _ => Err("identifier"),
+                }
+            }
+            / begin:position!() reciever:call_function() _ optional_left_bracket() _ expr:expression()  _ right_bracket() _ end:position!() {
+                Node::OptionalIndex(Box::new(reciever), Box::new(expr), Position::new(begin, end))
             }
-            / begin:position!() reciever:get_attr() _ left_bracket() _ expr:expression()  _ right_bracket() _ end:position!() {
-                Node::GetAttribute(Box::new(reciever), Box::new(expr), Position::new(begin, end))
+            / begin:position!() reciever:call_function() _ left_bracket() _ expr:expression()  _ right_bracket() _ end:position!() {
+                Node::Index(Box::new(reciever), Box::new(expr), Position::new(begin, end))
             }
-            / begin:position!() reciever:get_attr() _ left_bracket() _ elements:(expression() ** (_ comma() _))  _ right_bracket() _ end:position!() {
-                Node::GetAttribute(Box::new(reciever), Box::new(Node::List(elements, Position::new(begin, end))), Position::new(begin, end))
+            / begin:position!() reciever:call_function() _ left_bracket() _ elements:(expression() ** (_ comma() _))  _ right_bracket() _ end:position!() {
+                Node::Index(Box::new(reciever), Box::new(Node::List(elements, Position::new(begin, end))), Position::new(begin, end))
             }
             / hash()
 
+        // elements of tuple and set literals, which contain at least one comma
+        rule comma_separated() -> Vec<Node>
+            = first:expression() _ comma() _ rest:(expression() ** (_ comma() _)) _ comma()? {
+                let mut elements = vec![first];
+                elements.extend(rest);
+                elements
+            }
+
+        rule comprehension_clauses() -> Vec<Comprehension>
+            = first:comprehension_for() rest:(_ c:(comprehension_for() / comprehension_if()) { c })* {
+                let mut clauses = vec![first];
+                clauses.extend(rest);
+                clauses
+            }
+
+        rule comprehension_for() -> Comprehension
+            = keyword_for() _ names:(identifier() ++ (_ comma() _)) _ keyword_in() _ iterable:expression() {
+                Comprehension::For(names.into_iter().map(identifier_name).collect(), iterable)
+            }
+
+        rule comprehension_if() -> Comprehension
+            = keyword_if() _ condition:expression() {
+                Comprehension::If(condition)
+            }
+
         #[cache_left_rec]
         rule hash_element() -> (Node, Node)
             = _ key:expression() _ right_arrow() _ value:expression() {
@@ -449,18 +1104,28 @@ peg::parser! {
 
         #[cache_left_rec]
         rule hash() -> Node
-            = begin:position!() left_brace() _ elements:(hash_element() ** (_ comma() _)) _ comma()* _ right_brace() end:position!() {
+            = begin:position!() left_brace() _ pair:hash_element() _ clauses:comprehension_clauses() _ right_brace() end:position!() {
+                let (key, value) = pair;
+                Node::HashComprehension(Box::new(key), Box::new(value), clauses, Position::new(begin, end))
+            }
+            / begin:position!() left_brace() _ elements:(hash_element() ** (_ comma() _)) _ comma()* _ right_brace() end:position!() {
                 let mut out = vec![];
                 for (k ,v) in elements {
                     out.push((k, v));
                 }
                 Node::Hash(out, Position::new(begin, end))
             }
+            / begin:position!() left_brace() _ elements:comma_separated() _ right_brace() end:position!() {
+                Node::Set(elements, Position::new(begin, end))
+            }
             / list()
 
         #[cache_left_rec]
         rule list() -> Node
-            = begin:position!() left_bracket() elements:(expression() ** (_ comma()  _)) _ comma()*  right_bracket()  end:position!() {
+            = begin:position!() left_bracket() _ element:expression() _ clauses:comprehension_clauses() _ right_bracket() end:position!() {
+                Node::ListComprehension(Box::new(element), clauses, Position::new(begin, end))
+            }
+            / begin:position!() left_bracket() elements:(expression() ** (_ comma()  _)) _ comma()*  right_bracket()  end:position!() {
                 Node::List(elements, Position::new(begin, end))
             }
             / function()
@@ -509,7 +1174,7 @@ peg::parser! {
                 Node::Function {
                     arguments: args,
                     keyword_arguments: kwargs,
-                    sequence: Box::new(seq),
+                    sequence: Box::new(function_body(seq, Position::new(begin, end))),
                     position: Position::new(begin, end)
                 }
             }
@@ -517,12 +1182,19 @@ peg::parser! {
 
         #[cache_left_rec]
         rule atom() -> Node
-            = float_literal()
+            = imaginary_literal()
+            / float_literal()
             / integer_literal()
             / string_literal()
             / nil_literal()
             / bool_literal()
             / identifier()
+            / begin:position!() keyword_self() end:position!() {
+                Node::Identifier("self".to_string(), Position::new(begin, end))
+            }
+            / begin:position!() left_paren() _ elements:comma_separated() _ right_paren() end:position!() {
+                Node::Tuple(elements, Position::new(begin, end))
+            }
             / begin:position!() left_paren() _ seq:sequence() _ right_paren() end:position!() {
                 seq
             }
@@ -1,14 +1,21 @@
 use crate::core::environment::Environment;
-use crate::core::error::{generate_error_message, Error};
-use crate::core::object::Object;
+use crate::core::error::generate_error_message;
+use crate::core::gc::{self, GcStats};
+use crate::core::object::{BuiltInFn, MethodFn, Object};
 use crate::core::parser::tinyterp::program as parse;
 pub use crate::core::parser::Position;
-use std::collections::BTreeMap;
+use crate::core::scheduler;
 
 pub struct Runtime {
     pub env: Environment,
 }
 
+impl Default for Runtime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Runtime {
     pub fn new() -> Self {
         Runtime {
@@ -31,11 +38,64 @@ impl Runtime {
     }
 
     // add built-in(Rust) function
-    pub fn add_function(
-        &mut self,
-        name: &str,
-        function: fn(Vec<Object>, BTreeMap<String, Object>, Position) -> Result<Object, Error>,
-    ) {
+    pub fn add_function(&mut self, name: &str, function: BuiltInFn) {
         self.env.add_function(name, function);
     }
+
+    // protect the variable from being assigned or dropped by scripts. built-in functions are
+    // protected by default.
+    pub fn protect(&mut self, name: &str) {
+        self.env.protect(name);
+    }
+
+    // resume spawned tasks until all of them are finished or waiting
+    pub fn run_tasks(&mut self) -> Result<(), String> {
+        let _active = self.env.activate();
+        scheduler::run_tasks(Position::new(0, 0)).map_err(|e| generate_error_message(e, ""))
+    }
+
+    // advance the clock of `sleep` and run the tasks which woke up
+    pub fn advance_time(&mut self, seconds: f64) -> Result<(), String> {
+        let _active = self.env.activate();
+        scheduler::advance_time(seconds);
+        self.run_tasks()
+    }
+
+    // deliver the value to a channel created by the script, and run the tasks which received it
+    pub fn send(&mut self, channel: &Object, value: Object) -> Result<(), String> {
+        match channel {
+            Object::Channel(queue) => queue.borrow_mut().push_back(value),
+            _ => return Err(format!("{} is not a channel", channel.type_name())),
+        }
+        self.run_tasks()
+    }
+
+    // seconds until the earliest sleeping task wakes up
+    pub fn next_wakeup(&self) -> Option<f64> {
+        let _active = self.env.activate();
+        scheduler::next_wakeup()
+    }
+
+    // number of tasks which are not finished
+    pub fn task_count(&self) -> usize {
+        let _active = self.env.activate();
+        scheduler::task_count()
+    }
+
+    // reclaim objects kept alive only by reference cycles, and returns the number of them.
+    // collection also runs automatically as objects are created.
+    pub fn collect_garbage(&mut self) -> usize {
+        let _active = self.env.activate();
+        gc::collect()
+    }
+
+    pub fn gc_stats(&self) -> GcStats {
+        let _active = self.env.activate();
+        gc::stats()
+    }
+
+    // add built-in(Rust) method of the type, such as `add_method("str", "upper", f)`
+    pub fn add_method(&mut self, type_name: &str, name: &str, method: MethodFn) {
+        self.env.add_method(type_name, name, method);
+    }
 }
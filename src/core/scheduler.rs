@@ -0,0 +1,112 @@
+use crate::core::context;
+use crate::core::error::Error;
+use crate::core::object::Object;
+use crate::core::parser::Position;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+// buffer of a channel. sending never blocks.
+pub type Queue = Rc<RefCell<VecDeque<Object>>>;
+
+// value of `await` which suspends the task until the condition is satisfied
+#[derive(Debug, Clone)]
+pub enum Wait {
+    // seconds on the scheduler clock
+    Sleep(f64),
+    Receive(Queue),
+}
+
+#[derive(Debug)]
+enum State {
+    Ready,
+    Until(f64),
+    Receive(Queue),
+}
+
+#[derive(Debug)]
+struct Task {
+    coroutine: Object,
+    state: State,
+}
+
+// tasks are resumed only when the host calls `run_tasks`, and the clock advances only by
+// `advance_time`. each runtime has its own scheduler in its context.
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    tasks: VecDeque<Task>,
+    now: f64,
+}
+
+// the scheduler of the active runtime. it must not run scripts while borrowed.
+fn with_scheduler<T>(f: impl FnOnce(&mut Scheduler) -> T) -> T {
+    f(&mut context::current().scheduler.borrow_mut())
+}
+
+pub fn spawn(coroutine: Object) {
+    with_scheduler(|s| {
+        s.tasks.push_back(Task {
+            coroutine,
+            state: State::Ready,
+        })
+    });
+}
+
+// remove the first task which can be resumed, with the value sent to it
+fn take_ready() -> Option<(Object, Object)> {
+    with_scheduler(|s| {
+        let now = s.now;
+        let index = s.tasks.iter().position(|task| match &task.state {
+            State::Ready => true,
+            State::Until(time) => *time <= now,
+            State::Receive(queue) => !queue.borrow().is_empty(),
+        })?;
+        let task = s.tasks.remove(index)?;
+        let sent = match task.state {
+            State::Ready | State::Until(_) => Object::Nil,
+            State::Receive(queue) => queue.borrow_mut().pop_front()?,
+        };
+        Some((task.coroutine, sent))
+    })
+}
+
+// resume tasks in turn until all of them are finished or waiting. a task which fails is
+// removed and its error is returned.
+pub fn run_tasks(pos: Position) -> Result<(), Error> {
+    while let Some((coroutine, sent)) = take_ready() {
+        let state = match coroutine.resume(sent, pos)? {
+            None => continue,
+            Some(Object::Wait(Wait::Sleep(seconds))) => State::Until(now() + seconds),
+            Some(Object::Wait(Wait::Receive(queue))) => State::Receive(queue),
+            // plain `yield` gives other tasks a turn
+            Some(_) => State::Ready,
+        };
+        with_scheduler(|s| s.tasks.push_back(Task { coroutine, state }));
+    }
+    Ok(())
+}
+
+pub fn now() -> f64 {
+    with_scheduler(|s| s.now)
+}
+
+pub fn advance_time(seconds: f64) {
+    with_scheduler(|s| s.now += seconds);
+}
+
+// seconds until the earliest sleeping task wakes up
+pub fn next_wakeup() -> Option<f64> {
+    with_scheduler(|s| {
+        s.tasks
+            .iter()
+            .filter_map(|task| match task.state {
+                State::Until(time) => Some((time - s.now).max(0.0)),
+                _ => None,
+            })
+            .reduce(f64::min)
+    })
+}
+
+pub fn task_count() -> usize {
+    with_scheduler(|s| s.tasks.len())
+}
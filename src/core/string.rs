@@ -0,0 +1,181 @@
+use std::borrow::Borrow;
+use std::cell::{OnceCell, RefCell};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::rc::{Rc, Weak};
+
+// immutable string shared by reference, so that copying it is O(1). positions of chars are
+// computed on the first access by index and cached.
+#[derive(Clone)]
+pub struct Str(Rc<Inner>);
+
+struct Inner {
+    text: Box<str>,
+    // byte offsets of chars and the end of the text. `None` means that the text is ASCII, and
+    // byte offsets are char indices.
+    offsets: OnceCell<Option<Vec<usize>>>,
+}
+
+// interned strings, which are not kept alive by this table. dead entries are dropped when the
+// table grows to `limit`.
+struct Interned {
+    strings: HashMap<Box<str>, Weak<Inner>>,
+    limit: usize,
+}
+
+thread_local! {
+    static INTERNED: RefCell<Interned> = RefCell::new(Interned {
+        strings: HashMap::new(),
+        limit: 64,
+    });
+}
+
+impl Str {
+    pub fn new(text: impl Into<Box<str>>) -> Self {
+        Str(Rc::new(Inner {
+            text: text.into(),
+            offsets: OnceCell::new(),
+        }))
+    }
+
+    // shared string of the same text, used for identifiers in the source such as `name` of
+    // `x.name`
+    pub fn intern(text: &str) -> Self {
+        INTERNED.with(|interned| {
+            let mut interned = interned.borrow_mut();
+            if let Some(inner) = interned.strings.get(text).and_then(Weak::upgrade) {
+                return Str(inner);
+            }
+            if interned.strings.len() >= interned.limit {
+                interned.strings.retain(|_, s| s.strong_count() > 0);
+                interned.limit = (interned.strings.len() * 2).max(64);
+            }
+            let s = Str::new(text);
+            interned.strings.insert(text.into(), Rc::downgrade(&s.0));
+            s
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0.text
+    }
+
+    fn offsets(&self) -> Option<&[usize]> {
+        self.0
+            .offsets
+            .get_or_init(|| {
+                let text = &self.0.text;
+                if text.is_ascii() {
+                    None
+                } else {
+                    let mut offsets: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
+                    offsets.push(text.len());
+                    Some(offsets)
+                }
+            })
+            .as_deref()
+    }
+
+    // number of chars
+    pub fn char_count(&self) -> usize {
+        match self.offsets() {
+            None => self.0.text.len(),
+            Some(offsets) => offsets.len() - 1,
+        }
+    }
+
+    // chars from `start` (inclusive) to `end` (exclusive), or `None` if out of range
+    pub fn char_slice(&self, start: usize, end: usize) -> Option<&str> {
+        if start > end || end > self.char_count() {
+            return None;
+        }
+        match self.offsets() {
+            None => Some(&self.0.text[start..end]),
+            Some(offsets) => Some(&self.0.text[offsets[start]..offsets[end]]),
+        }
+    }
+
+    pub fn char_at(&self, index: usize) -> Option<&str> {
+        self.char_slice(index, index.checked_add(1)?)
+    }
+}
+
+impl Deref for Str {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for Str {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<String> for Str {
+    fn from(text: String) -> Self {
+        Str::new(text)
+    }
+}
+
+impl From<&str> for Str {
+    fn from(text: &str) -> Self {
+        Str::new(text)
+    }
+}
+
+impl PartialEq for Str {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0) || self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Str {}
+
+impl PartialEq<str> for Str {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<String> for Str {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialOrd for Str {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Str {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for Str {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Display for Str {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+// quoted like `String`
+impl fmt::Debug for Str {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
@@ -1,6 +1,8 @@
 pub mod builtin_functions;
 pub mod core;
 pub use crate::core::environment::Environment;
+pub use crate::core::gc::GcStats;
 pub use crate::core::object::Object;
 pub use crate::core::parser::Position;
 pub use crate::core::runtime::Runtime;
+pub use crate::core::string::Str;
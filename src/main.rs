@@ -1,11 +1,8 @@
-mod builtin_functions;
-mod core;
-
 use std::fs::File;
 use std::io::prelude::*;
 
-use crate::builtin_functions::load_builtin_stdio;
-use crate::core::runtime::Runtime;
+use tinyterp::builtin_functions::load_builtin_stdio;
+use tinyterp::Runtime;
 
 use std::env;
 
@@ -44,7 +41,15 @@ fn main() {
         let mut rt = Runtime::new();
         load_builtin_stdio(&mut rt.env);
 
-        let result = rt.evaluate(&content);
+        let mut result = rt.evaluate(&content).and_then(|_| rt.run_tasks());
+        // drive sleeping tasks in real time
+        while let (Ok(()), Some(seconds)) = (&result, rt.next_wakeup()) {
+            // `sleep` accepts only representable durations, so this fails only on rounding
+            if let Ok(duration) = std::time::Duration::try_from_secs_f64(seconds) {
+                std::thread::sleep(duration);
+            }
+            result = rt.advance_time(seconds);
+        }
         if let Err(err) = result {
             println!("Error: {}", err);
         }
@@ -1,4 +1,4 @@
-use tinyterp::Runtime;
+use tinyterp::{Object, Runtime};
 
 pub fn run(code: &str) -> String {
     let mut rt = Runtime::new();
@@ -6,6 +6,12 @@ pub fn run(code: &str) -> String {
     rt.evaluate(code).unwrap().to_string()
 }
 
+pub fn run_err(code: &str) -> String {
+    let mut rt = Runtime::new();
+    tinyterp::builtin_functions::load_builtin_stdio(&mut rt.env);
+    rt.evaluate(code).unwrap_err()
+}
+
 #[test]
 fn parse_newline() {
     run("1\n\n2");
@@ -41,6 +47,23 @@ fn functions() {
     assert_eq!(run("f = (x, a=1) -> {x + a}; f(1, a=2)"), "3");
 }
 
+#[test]
+fn default_arguments_scope() {
+    // defaults are evaluated where the function is defined, even if the caller shadows them
+    assert_eq!(
+        run("n = 10; f = (x, a=n) -> {x + a}; g = (n) -> f(1); g(1)"),
+        "11"
+    );
+    // calls made by builtins see the same defaults as direct calls
+    assert_eq!(
+        run("n = 10; f = (x, a=n) -> {x + a}; list(map([1, 2], f))"),
+        "[11, 12]"
+    );
+    assert_eq!(run("n = 10; f = (x, a=n) -> {x + a}; n = 20; f(1)"), "21");
+    // other parameters are not visible to the defaults
+    assert!(run_err("f = (x, a=x) -> a; g = (x) -> f(1); g(2)").contains("VariableNotInitialized"));
+}
+
 #[test]
 fn list() {
     assert_eq!(run("[]"), "[]");
@@ -58,3 +81,1506 @@ fn fibonacchi() {
 fn fizzbuzz() {
     run(include_str!("../examples/fizzbuzz.tinyterp"));
 }
+
+#[test]
+fn hash() {
+    assert_eq!(run("h = {}; h"), "{}");
+    assert_eq!(run("{\"a\" -> 1, \"b\" -> 2}"), "{\"a\" -> 1, \"b\" -> 2}");
+    assert_eq!(run("h = {\"a\" -> 1, \"b\" -> 2}; h[\"b\"]"), "2");
+}
+
+#[test]
+fn match_literals() {
+    assert_eq!(run("match 0 { 0 -> \"zero\", _ -> \"other\" }"), "\"zero\"");
+    assert_eq!(run("match \"a\" { \"b\" -> 1, \"a\" -> 2 }"), "2");
+    assert_eq!(run("match nil { 0 -> 1, _ -> 2 }"), "2");
+}
+
+#[test]
+fn match_alternatives_and_ranges() {
+    assert_eq!(
+        run("match 2 { 1 | 2 -> \"small\", _ -> \"other\" }"),
+        "\"small\""
+    );
+    assert_eq!(
+        run("match 9 { 3..10 -> \"medium\", _ -> \"other\" }"),
+        "\"medium\""
+    );
+    assert_eq!(
+        run("match 10 { 3..10 -> \"medium\", _ -> \"other\" }"),
+        "\"other\""
+    );
+    assert_eq!(
+        run("match 20 { 10..=20 -> \"large\", _ -> \"other\" }"),
+        "\"large\""
+    );
+}
+
+#[test]
+fn match_types_and_guards() {
+    assert_eq!(
+        run("match 0.5 { int -> \"int\", float -> \"float\" }"),
+        "\"float\""
+    );
+    assert_eq!(run("match -5 { n: int if n < 0 -> n, _ -> 0 }"), "-5");
+    assert_eq!(run("match 5 { n: int if n < 0 -> n, _ -> 0 }"), "0");
+}
+
+#[test]
+fn match_lists_and_hashes() {
+    assert_eq!(
+        run("match [] { [] -> \"empty\", _ -> \"other\" }"),
+        "\"empty\""
+    );
+    assert_eq!(run("match [1, 2, 3] { [first, ..rest] -> rest }"), "[2, 3]");
+    assert_eq!(
+        run("match {\"name\" -> \"a\"} { {\"name\" -> name} -> name }"),
+        "\"a\""
+    );
+    assert_eq!(
+        run("match {\"age\" -> 1} { {\"name\" -> name} -> name, _ -> nil }"),
+        "nil"
+    );
+}
+
+#[test]
+fn match_without_arm() {
+    assert!(run_err("match 1 { 2 -> 3 }").contains("MatchError"));
+}
+
+#[test]
+fn format_string() {
+    assert_eq!(run("x = 3.14159; f\"x={x:.3f}\""), "\"x=3.142\"");
+    assert_eq!(run("xs = [1, 2]; f\"{len(xs)} items\""), "\"2 items\"");
+    assert_eq!(run("f\"{{}}\""), "\"{}\"");
+    assert_eq!(run("f\"{\"ab\":>4}|{255:#x}\""), "\"  ab|0xff\"");
+}
+
+#[test]
+fn format_numbers() {
+    assert_eq!(run("format(1234567, \",\")"), "\"1,234,567\"");
+    assert_eq!(run("format(255, \"#x\")"), "\"0xff\"");
+    assert_eq!(run("format(5, \"04b\")"), "\"0101\"");
+    assert_eq!(run("format(-42, \"+06d\")"), "\"-00042\"");
+    assert_eq!(run("format(0.5, \".1%\")"), "\"50.0%\"");
+    assert_eq!(run("format(12345.678, \"e\")"), "\"1.234568e+04\"");
+    assert!(run_err("format(1.5, \"x\")").contains("TypeError"));
+}
+
+#[test]
+fn format_alignment() {
+    assert_eq!(run("format(\"ab\", \">4\")"), "\"  ab\"");
+    assert_eq!(run("format(\"ab\", \"^6\")"), "\"  ab  \"");
+    assert_eq!(run("format(\"ab\", \"*<4\")"), "\"ab**\"");
+}
+
+#[test]
+fn format_list() {
+    assert_eq!(run("format([1, 2], \".1f\")"), "\"[1.0, 2.0]\"");
+}
+
+#[test]
+fn format_grouping_by_radix() {
+    assert_eq!(run("format(255, \"_b\")"), "\"1111_1111\"");
+    assert_eq!(run("format(65536, \"#_x\")"), "\"0x1_0000\"");
+    assert_eq!(run("format(4095, \"_o\")"), "\"7777\"");
+    assert_eq!(run("format(1234567, \"_\")"), "\"1_234_567\"");
+    assert!(run_err("format(255, \",x\")").contains("ValueError"));
+}
+
+#[test]
+fn format_spec_overflow() {
+    assert!(run_err("format(123, \"99999999999999999999\")").contains("ValueError"));
+    assert!(run_err("format(1, \".99999999999999999999f\")").contains("ValueError"));
+    assert!(run_err("format(1, \"999999999999\")").contains("ValueError"));
+    assert!(run_err("f\"{1:.20000f}\"").contains("ValueError"));
+    assert_eq!(run("len(format(1, \"10000\"))"), "10000");
+}
+
+#[test]
+fn integer_literals() {
+    assert_eq!(run("0xff"), "255");
+    assert_eq!(run("0b1010"), "10");
+    assert_eq!(run("0o17"), "15");
+    assert_eq!(run("1_000_000"), "1000000");
+    assert_eq!(run("-0x10"), "-16");
+}
+
+#[test]
+fn float_literals() {
+    assert_eq!(run("1e-3"), "0.001");
+    assert_eq!(run(".5"), "0.5");
+    assert_eq!(run("2.5e3"), "2500.0");
+    assert_eq!(run("1_000.25"), "1000.25");
+}
+
+#[test]
+fn string_literals() {
+    assert_eq!(run("\"a\\tb\\r\\0\""), "\"a\\tb\\r\\0\"");
+    assert_eq!(run("\"\\u{3042}\""), "\"あ\"");
+    assert_eq!(run("r\"C:\\new\""), "\"C:\\\\new\"");
+    assert_eq!(run("'say \"hi\"'"), "\"say \\\"hi\\\"\"");
+    assert_eq!(run("\"\"\"a\n\"b\" c\"\"\""), "\"a\\n\\\"b\\\" c\"");
+}
+
+#[test]
+fn unknown_escape_sequences() {
+    assert!(run_err("\"\\q\"").contains("Syntax Error"));
+    assert!(run_err("'\\q'").contains("Syntax Error"));
+    assert!(run_err("\"\"\"\\q\"\"\"").contains("Syntax Error"));
+    assert!(run_err("f\"{1}\\q\"").contains("Syntax Error"));
+    assert_eq!(run("r\"\\q\""), "\"\\\\q\"");
+}
+
+#[test]
+fn invalid_unicode_escapes() {
+    assert!(run_err("\"\\u{110000}\"").contains("invalid unicode escape"));
+    assert!(run_err("\"\\u{d800}\"").contains("invalid unicode escape"));
+    assert!(run_err("\"\\u{}\"").contains("Syntax Error"));
+    assert!(run_err("\"\\u{1234567}\"").contains("Syntax Error"));
+    assert!(run_err("\"\\u{12\"").contains("Syntax Error"));
+    assert!(run_err("\"\\u12\"").contains("Syntax Error"));
+}
+
+#[test]
+fn compound_assign() {
+    assert_eq!(run("x = 1; x += 2; x"), "3");
+    assert_eq!(run("x = 3; x *= 10; x -= 5; x"), "25");
+    assert_eq!(run("x = 25; x /= 5; x **= 2; x"), "25");
+    assert_eq!(run("x = 17; x %= 5; x"), "2");
+    assert_eq!(run("s = \"a\"; s += \"b\"; s"), "\"ab\"");
+}
+
+#[test]
+fn compound_assign_to_elements() {
+    assert_eq!(run("h = {\"a\" -> 1}; h[\"a\"] += 1; h"), "{\"a\" -> 2}");
+    assert_eq!(run("h = {}; h[\"b\"] = 0; h"), "{\"b\" -> 0}");
+    assert_eq!(
+        run("m = [[1, 2], [3, 4]]; m[1][0] += 100; m"),
+        "[[1, 2], [103, 4]]"
+    );
+    assert_eq!(run("h = {\"p\" -> {\"x\" -> 1}}; h.p.x += 41; h.p.x"), "42");
+    assert!(run_err("xs = [1]; xs[3] += 1").contains("IndexOutOfRange"));
+}
+
+#[test]
+fn modulo() {
+    assert_eq!(run("7 % 3"), "1");
+    assert_eq!(run("-7 % 3"), "2");
+    assert_eq!(run("7 % -3"), "-2");
+    assert_eq!(run("-7.5 % 2"), "0.5");
+    assert!(run_err("1 % 0").contains("DivideByZero"));
+}
+
+#[test]
+fn floor_division() {
+    assert_eq!(run("7 // 2"), "3");
+    assert_eq!(run("-7 // 2"), "-4");
+    assert_eq!(run("7 // -2"), "-4");
+    assert_eq!(run("7.5 // 2"), "3.0");
+    assert!(run_err("1 // 0").contains("DivideByZero"));
+}
+
+#[test]
+fn bitwise_operators() {
+    assert_eq!(run("6 & 3"), "2");
+    assert_eq!(run("6 | 3"), "7");
+    assert_eq!(run("6 ^ 3"), "5");
+    assert_eq!(run("~5"), "-6");
+    assert_eq!(run("1 << 10"), "1024");
+    assert_eq!(run("-16 >> 2"), "-4");
+    assert_eq!(run("x = 5; x //= 2; x <<= 3; x |= 1; x"), "17");
+    assert!(run_err("1 << 64").contains("ValueError"));
+    assert!(run_err("1 >> -1").contains("ValueError"));
+}
+
+#[test]
+fn operator_precedence() {
+    assert_eq!(run("1 + 2 << 1"), "6");
+    assert_eq!(run("1 | 2 == 3"), "true");
+    assert_eq!(run("2 * 3 % 4"), "2");
+}
+
+#[test]
+fn checked_arithmetic() {
+    assert!(run_err("9223372036854775807 + 1").contains("OverflowError"));
+    assert!(run_err("-9223372036854775807 - 2").contains("OverflowError"));
+    assert!(run_err("3037000500 * 3037000500").contains("OverflowError"));
+    assert!(run_err("-(-9223372036854775807 - 1)").contains("OverflowError"));
+    assert!(run_err("(-9223372036854775807 - 1) // -1").contains("OverflowError"));
+    assert!(run_err("1 << 63").contains("OverflowError"));
+}
+
+#[test]
+fn int_conversion_overflow() {
+    assert!(run_err("int(1e30)").contains("OverflowError"));
+    assert!(run_err("int(\"99999999999999999999\")").contains("OverflowError"));
+    assert!(run_err("int(\"-99999999999999999999\")").contains("OverflowError"));
+    assert!(run_err("int(\"12a\")").contains("ParseError"));
+}
+
+#[test]
+fn pow() {
+    assert_eq!(run("2 ** 62"), "4611686018427387904");
+    assert_eq!(run("2 ** -2"), "0.25");
+    assert_eq!(run("(-1) ** 9999999999"), "-1");
+    assert!(run_err("2 ** 63").contains("OverflowError"));
+    assert!(run_err("0 ** -1").contains("DivideByZero"));
+}
+
+#[test]
+fn pow_type_errors() {
+    assert!(run_err("2 ** \"a\"").contains("cannot raise int ** str"));
+    assert!(run_err("2.0 ** [1]").contains("cannot raise float ** list"));
+    assert!(run_err("\"a\" ** 2").contains("cannot raise str ** int"));
+    assert!(run_err("x = 2; x **= nil").contains("cannot raise int ** nil"));
+}
+
+#[test]
+fn not_equal() {
+    assert_eq!(run("1 != 2"), "true");
+    assert_eq!(run("1 != 1"), "false");
+    assert_eq!(run("1 == 1.0"), "true");
+    assert_eq!(run("[1, 2] != [1, 2]"), "false");
+    assert_eq!(run("f = (x) -> x; f == f"), "true");
+    assert_eq!(
+        run("{\"a\" -> 1, \"b\" -> 2} == {\"b\" -> 2, \"a\" -> 1}"),
+        "true"
+    );
+}
+
+#[test]
+fn ordering() {
+    assert_eq!(run("\"abc\" < \"abd\""), "true");
+    assert_eq!(run("\"b\" > \"abc\""), "true");
+    assert_eq!(run("[1, 2] < [1, 3]"), "true");
+    assert_eq!(run("[1, 2] < [1, 2, 0]"), "true");
+    assert_eq!(run("false < true"), "true");
+    assert!(run_err("1 < \"a\"").contains("TypeError"));
+}
+
+#[test]
+fn comparison_chain() {
+    assert_eq!(run("x = 5; 0 <= x < 10"), "true");
+    assert_eq!(run("x = 5; 0 <= x < 5"), "false");
+    assert_eq!(run("3 > 2 > 1"), "true");
+}
+
+#[test]
+fn int_float_comparison() {
+    assert_eq!(run("9007199254740993 <= 9007199254740992.0"), "false");
+    assert_eq!(run("9007199254740993 >= 9007199254740992.0"), "true");
+    assert_eq!(run("9007199254740993 == 9007199254740992.0"), "false");
+    assert_eq!(run("9007199254740992.0 < 9007199254740993"), "true");
+    assert_eq!(run("9223372036854775807 < 9223372036854775807.0"), "true");
+    assert_eq!(run("[1 < 1.5, -1 > -1.5, 1 <= 1.0]"), "[true, true, true]");
+}
+
+#[test]
+fn int_float_comparison_with_special_floats() {
+    assert_eq!(run("float(\"inf\") > 9223372036854775807"), "true");
+    assert_eq!(run("float(\"-inf\") < -9223372036854775807"), "true");
+    assert_eq!(run("1 < float(\"nan\")"), "false");
+    assert_eq!(run("1 >= float(\"nan\")"), "false");
+}
+
+#[test]
+fn int_float_sort_and_membership() {
+    assert_eq!(
+        run("sort([9007199254740993, 9007199254740992.0, 9007199254740992])"),
+        "[9007199254740992.0, 9007199254740992, 9007199254740993]"
+    );
+    assert_eq!(
+        run("9007199254740992.0 in 9007199254740992..9007199254740993"),
+        "true"
+    );
+    assert_eq!(
+        run("9007199254740992.0 in 9007199254740993..9007199254740995"),
+        "false"
+    );
+}
+
+#[test]
+fn sort() {
+    assert_eq!(run("sort([3, 1.5, 2, -1])"), "[-1, 1.5, 2, 3]");
+    assert_eq!(
+        run("sort([\"b\", \"a\", \"c\"], reverse=true)"),
+        "[\"c\", \"b\", \"a\"]"
+    );
+    assert_eq!(run("sort([[2], [1, 5], [1]])"), "[[1], [1, 5], [2]]");
+    assert!(run_err("sort([1, \"a\"])").contains("TypeError"));
+}
+
+#[test]
+fn sort_is_stable() {
+    // equal elements keep their order even if reversed
+    assert_eq!(
+        run("sort([1, 1.0, 0, 0.0], reverse=true)"),
+        "[1, 1.0, 0, 0.0]"
+    );
+}
+
+#[test]
+fn min_and_max() {
+    assert_eq!(run("min(3, 1, 2)"), "1");
+    assert_eq!(run("max([3, 1, 2])"), "3");
+}
+
+#[test]
+fn logical_operators() {
+    assert_eq!(run("nil or \"default\""), "\"default\"");
+    assert_eq!(run("0 or 1"), "0");
+    assert_eq!(run("1 and 2"), "2");
+    assert_eq!(run("nil and 1"), "nil");
+    // the right operand is not evaluated
+    assert_eq!(run("false and x"), "false");
+}
+
+#[test]
+fn nil_coalesce() {
+    assert_eq!(run("nil ?? 1"), "1");
+    assert_eq!(run("false ?? 1"), "false");
+    assert_eq!(run("2 ?? 1"), "2");
+    assert_eq!(run("nil ?? nil ?? 3"), "3");
+}
+
+#[test]
+fn optional_chaining() {
+    assert_eq!(run("h = {\"a\" -> {\"b\" -> 1}}; h?.a?.b"), "1");
+    assert_eq!(run("h = {\"a\" -> {\"b\" -> 1}}; h?.x?.b"), "nil");
+    assert_eq!(run("nil?.a"), "nil");
+    assert_eq!(run("h = {\"a\" -> 1}; h?[\"a\"]"), "1");
+    assert_eq!(run("[1]?[5]"), "nil");
+    assert_eq!(run("xs = nil; xs?[0] ?? \"empty\""), "\"empty\"");
+    assert!(run_err("h = {}; h.x").contains("KeyError"));
+}
+
+#[test]
+fn membership() {
+    assert_eq!(run("2 in [1, 2]"), "true");
+    assert_eq!(run("3 not in [1, 2]"), "true");
+    assert_eq!(run("\"ell\" in \"hello\""), "true");
+    assert_eq!(run("\"a\" in {\"a\" -> 1}"), "true");
+    assert_eq!(run("1 in {\"a\" -> 1}"), "false");
+    assert_eq!(run("index = 1; inside = [1]; index in inside"), "true");
+    assert!(run_err("1 in \"abc\"").contains("TypeError"));
+    assert!(run_err("1 in 5").contains("TypeError"));
+}
+
+#[test]
+fn range_membership() {
+    assert_eq!(run("5 in 0..10"), "true");
+    assert_eq!(run("10 in 0..10"), "false");
+    assert_eq!(run("10 in 0..=10"), "true");
+    assert_eq!(run("2.5 in 1..3"), "true");
+    assert_eq!(run("\"a\" in 0..3"), "false");
+}
+
+#[test]
+fn membership_precedence() {
+    assert_eq!(run("x = 3; not x in [3]"), "false");
+    assert_eq!(run("x = 3; x in [1] or x in [3]"), "true");
+}
+
+#[test]
+fn identity() {
+    assert_eq!(run("f = (x) -> x; f is f"), "true");
+    assert_eq!(run("f = (x) -> x; g = (x) -> x; f is g"), "false");
+    assert_eq!(run("f = (x) -> x; g = (x) -> x; f is not g"), "true");
+    assert_eq!(run("nil is nil"), "true");
+    assert_eq!(run("1 is 1.0"), "false");
+}
+
+#[test]
+fn container_identity() {
+    // containers are identical only to the same object
+    assert_eq!(run("[1] is [1]"), "false");
+    assert_eq!(run("a = [1]; b = a; a is b"), "true");
+    assert_eq!(run("h = {\"k\" -> 1}; h is h"), "true");
+    assert_eq!(run("h = {\"k\" -> 1}; h is {\"k\" -> 1}"), "false");
+}
+
+#[test]
+fn range_len() {
+    assert_eq!(run("len(0..10)"), "10");
+    assert_eq!(run("len(0..=9)"), "10");
+    assert_eq!(run("len(3..3)"), "0");
+    assert_eq!(run("len(5..2)"), "0");
+    assert_eq!(
+        run("len((-9223372036854775807 - 1)..-1)"),
+        "9223372036854775807"
+    );
+    assert_eq!(
+        run("len(9223372036854775807..(-9223372036854775807 - 1))"),
+        "0"
+    );
+}
+
+#[test]
+fn range_len_overflow() {
+    assert!(
+        run_err("len((-9223372036854775807 - 1)..9223372036854775807)").contains("OverflowError")
+    );
+    assert!(run_err("len(-1..9223372036854775807)").contains("OverflowError"));
+}
+
+#[test]
+fn pipe() {
+    assert_eq!(run("-2 |> abs |> str"), "\"2.0\"");
+    assert_eq!(run("[3, 1, 2] |> sort(reverse=true)"), "[3, 2, 1]");
+    assert_eq!(run("6 |> (x) -> {x + 1}"), "7");
+    assert_eq!(run("1 | 2"), "3");
+}
+
+#[test]
+fn string_methods() {
+    assert_eq!(run("\" Abc \".trim().upper()"), "\"ABC\"");
+    assert_eq!(run("\"a,b\".split(\",\")"), "[\"a\", \"b\"]");
+    assert_eq!(run("\"a b\".replace(\" \", \"-\")"), "\"a-b\"");
+    assert_eq!(run("\"abc\".len()"), "3");
+    assert!(run_err("1.upper()").contains("AttributeError"));
+}
+
+#[test]
+fn list_methods() {
+    assert_eq!(
+        run("xs = [1, 2]; xs.push(3); xs.insert(0, 0); xs"),
+        "[0, 1, 2, 3]"
+    );
+    assert_eq!(run("xs = [1, 2]; [xs.pop(), xs]"), "[2, [1]]");
+    assert_eq!(run("[0, 1, 2].join(\"-\")"), "\"0-1-2\"");
+    assert!(run_err("[].pop()").contains("IndexOutOfRange"));
+}
+
+#[test]
+fn hash_methods() {
+    assert_eq!(
+        run("h = {\"a\" -> [1]}; h.a.push(2); h"),
+        "{\"a\" -> [1, 2]}"
+    );
+    assert_eq!(
+        run("h = {\"a\" -> 1, \"b\" -> 2}; h.keys()"),
+        "[\"a\", \"b\"]"
+    );
+    assert_eq!(run("h = {}; h.get(\"b\", 0)"), "0");
+    assert_eq!(
+        run("h = {\"a\" -> 1, \"b\" -> 2}; [h.remove(\"a\"), h]"),
+        "[1, {\"b\" -> 2}]"
+    );
+    // keys of the hash take precedence over methods
+    assert_eq!(run("h = {\"len\" -> 5}; h.len"), "5");
+}
+
+#[test]
+fn index_does_not_resolve_methods() {
+    assert!(run_err("h = {}; h[\"keys\"]").contains("KeyError"));
+    assert_eq!(run("h = {}; h?[\"keys\"]"), "nil");
+    assert_eq!(run("h = {}; h.keys()"), "[]");
+    assert!(run_err("\"abc\"[\"upper\"]").contains("TypeError"));
+}
+
+#[test]
+fn optional_method_calls() {
+    assert_eq!(run("xs = [1]; xs?.push(2); xs"), "[1, 2]");
+    assert_eq!(
+        run("h = {\"a\" -> [1]}; h?[\"a\"].push(2); h"),
+        "{\"a\" -> [1, 2]}"
+    );
+    assert_eq!(
+        run("h = {\"a\" -> [1]}; h?.a?.push(2); h"),
+        "{\"a\" -> [1, 2]}"
+    );
+    assert!(run_err("h = {}; h?[\"a\"].push(2)").contains("AttributeError"));
+}
+
+#[test]
+fn optional_method_calls_in_functions() {
+    assert_eq!(
+        run("f = (xs) -> xs?.push(2); ys = [1]; [f(ys), ys]"),
+        "[nil, [1]]"
+    );
+    assert_eq!(run("f = (xs) -> { xs?.push(2); xs }; f([1])"), "[1, 2]");
+}
+
+#[test]
+fn detached_methods() {
+    assert_eq!(run("f = \"abc\".upper; f()"), "\"ABC\"");
+    assert_eq!(run("xs = [1, 2]; f = xs.len; xs.push(3); f()"), "2");
+    assert_eq!(run("h = {\"a\" -> 1}; f = h.get; f(\"a\")"), "1");
+}
+
+#[test]
+fn detached_methods_cannot_modify_receiver() {
+    assert!(run_err("xs = [1]; f = xs.push; f(2)").contains("TypeError"));
+    assert!(run_err("xs = [1]; f = xs.pop; drop xs; f()").contains("TypeError"));
+    assert!(run_err("s = {1,}; list(map([2], s.add))").contains("TypeError"));
+}
+
+#[test]
+fn host_methods() {
+    fn double(
+        receiver: &mut Object,
+        _args: Vec<Object>,
+        _kwargs: std::collections::BTreeMap<String, Object>,
+        _pos: tinyterp::Position,
+    ) -> Result<Object, tinyterp::core::error::Error> {
+        match receiver {
+            Object::Int(i) => Ok(Object::Int(*i * 2)),
+            _ => Ok(Object::Nil),
+        }
+    }
+    let mut rt = Runtime::new();
+    rt.add_method("int", "double", double);
+    assert_eq!(rt.evaluate("21.double()").unwrap().to_string(), "42");
+    // methods added to a runtime are not visible to others
+    let mut other = Runtime::new();
+    assert!(other
+        .evaluate("21.double()")
+        .unwrap_err()
+        .contains("AttributeError"));
+    assert_eq!(rt.evaluate("[1, 2].len()").unwrap().to_string(), "2");
+}
+
+#[test]
+fn structs() {
+    assert_eq!(
+        run("struct Point { x, y }; Point(1, y=2)"),
+        "Point(x=1, y=2)"
+    );
+    assert_eq!(run("struct Point { x, y }; Point(1, 2).x"), "1");
+    assert_eq!(run("struct Point { x, y }; type(Point(1, 2))"), "\"Point\"");
+    assert_eq!(run("struct Point { x, y }; type(Point)"), "\"struct\"");
+}
+
+#[test]
+fn struct_methods() {
+    assert_eq!(
+        run("struct Point { x, y, norm2() -> self.x ** 2 + self.y ** 2 }; Point(1, 2).norm2()"),
+        "5"
+    );
+    assert_eq!(
+        run(
+            "struct Point { x, y, add(other) -> Point(self.x + other.x, self.y + other.y) }
+            Point(1, 2).add(Point(1, 1))"
+        ),
+        "Point(x=2, y=3)"
+    );
+    assert_eq!(
+        run("struct Point { x, y, shift(dx) -> { self.x += dx; self } }; p = Point(1, 2); p.shift(10); p"),
+        "Point(x=11, y=2)"
+    );
+}
+
+#[test]
+fn instances_are_shared() {
+    assert_eq!(
+        run("struct Point { x, y }; p = Point(1, 2); q = p; q.y = 5; p"),
+        "Point(x=1, y=5)"
+    );
+}
+
+#[test]
+fn instance_equality() {
+    assert_eq!(
+        run("struct Point { x, y }; Point(1, 2) == Point(1, 2)"),
+        "true"
+    );
+    assert_eq!(
+        run("struct Point { x, y }; Point(1, 2) is Point(1, 2)"),
+        "false"
+    );
+    assert_eq!(
+        run("struct Point { x, y }; p = Point(1, 2); p is p"),
+        "true"
+    );
+}
+
+#[test]
+fn struct_errors() {
+    assert!(run_err("struct Point { x, y }; Point(1)").contains("ArgumentError"));
+    assert!(run_err("struct Point { x, y }; Point(1, 2).z").contains("AttributeError"));
+    assert!(run_err("struct Point { x, y }; p = Point(1, 2); p.z = 1").contains("AttributeError"));
+    assert_eq!(run("struct Point { x, y }; Point(1, 2)?.z"), "nil");
+}
+
+#[test]
+fn cyclic_instances() {
+    assert_eq!(
+        run("struct Node { value, next }; n = Node(1, nil); n.next = n; n"),
+        "Node(value=1, next=Node(...))"
+    );
+}
+
+#[test]
+fn cyclic_instance_equality() {
+    assert_eq!(
+        run("struct N { next }; a = N(nil); a.next = a; b = N(nil); b.next = b; a == b"),
+        "true"
+    );
+    assert_eq!(
+        run("struct N { next }; a = N(nil); a.next = a; b = N(nil); b.next = b; a != b"),
+        "false"
+    );
+    assert_eq!(
+        run("struct N { next }; a = N(nil); a.next = a; N(a) == a"),
+        "true"
+    );
+    assert_eq!(
+        run("struct N { next }; a = N(nil); a.next = a; N(a) == N(1)"),
+        "false"
+    );
+}
+
+#[test]
+fn arithmetic_overloading() {
+    assert_eq!(
+        run("struct V { x, __add__(o) -> V(self.x + o.x) }; (V(1) + V(2)).x"),
+        "3"
+    );
+    assert_eq!(
+        run("struct V { x, __mul__(k) -> V(self.x * k) }; (V(2) * 3).x"),
+        "6"
+    );
+    assert_eq!(
+        run("struct V { x, __rmul__(k) -> V(self.x * k) }; (3 * V(2)).x"),
+        "6"
+    );
+    assert_eq!(
+        run("struct V { x, __neg__() -> V(-self.x) }; n = -V(2); n.x"),
+        "-2"
+    );
+    assert!(run_err("struct V { x }; V(1) - V(1)").contains("TypeError"));
+}
+
+#[test]
+fn comparison_overloading() {
+    assert_eq!(
+        run("struct V { x, __eq__(o) -> self.x == o.x }; V(1) == V(1)"),
+        "true"
+    );
+    assert_eq!(
+        run("struct V { x, __eq__(o) -> self.x == o.x }; V(1) != V(1)"),
+        "false"
+    );
+    assert_eq!(
+        run("struct V { x, __eq__(o) -> self.x == o.x }; V(1) in [V(1)]"),
+        "true"
+    );
+    assert_eq!(
+        run("struct V { x, __eq__(o) -> type(o) == \"V\" and self.x == o.x }; V(1) == 1"),
+        "false"
+    );
+}
+
+#[test]
+fn ordering_overloading() {
+    assert_eq!(
+        run("struct V { x, __lt__(o) -> self.x < o.x }; V(0) < V(1)"),
+        "true"
+    );
+    assert_eq!(
+        run("struct V { x, __lt__(o) -> self.x < o.x }; v = V(1); v <= v"),
+        "true"
+    );
+    assert_eq!(
+        run("struct V { x, __lt__(o) -> self.x < o.x }; v = V(1); v > v"),
+        "false"
+    );
+    assert_eq!(
+        run("struct V { x, __lt__(o) -> self.x < o.x }; [v.x for v in sort([V(3), V(1), V(2)])]"),
+        "[1, 2, 3]"
+    );
+}
+
+#[test]
+fn protocol_overloading() {
+    assert_eq!(
+        run("struct V { x, y, __index__(i) -> if i == 0 then self.x else self.y }; V(1, 2)[1]"),
+        "2"
+    );
+    assert_eq!(run("struct V { __len__() -> 2 }; len(V())"), "2");
+    assert_eq!(
+        run("struct V { x, __str__() -> f\"<{self.x}>\" }; str(V(1))"),
+        "\"<1>\""
+    );
+    assert_eq!(
+        run("struct V { x, __str__() -> f\"<{self.x}>\" }; [V(1)]"),
+        "[<1>]"
+    );
+}
+
+#[test]
+fn str_overloading_errors() {
+    // errors of `__str__` are not hidden by `str` and f-strings
+    assert!(run_err("struct B { x, __str__() -> 1 / self.x }; str(B(0))").contains("DivideByZero"));
+    assert!(
+        run_err("struct B { x, __str__() -> 1 / self.x }; f\"{B(0)}\"").contains("DivideByZero")
+    );
+    assert!(
+        run_err("struct B { x, __str__() -> 1 / self.x }; str(B(1))")
+            .contains("__str__ must return str")
+    );
+}
+
+#[test]
+fn enums() {
+    assert_eq!(
+        run("enum Shape { Circle(r), Empty }; Circle(2)"),
+        "Circle(r=2)"
+    );
+    assert_eq!(
+        run("enum Shape { Rect(w, h) }; Shape.Rect(2, 3)"),
+        "Rect(w=2, h=3)"
+    );
+    assert_eq!(run("enum Shape { Circle(r), Empty }; Empty"), "Empty");
+    assert_eq!(
+        run("enum Shape { Circle(r), Empty }; Shape.Empty == Empty"),
+        "true"
+    );
+    assert_eq!(
+        run("enum Shape { Circle(r), Empty }; type(Empty)"),
+        "\"Shape\""
+    );
+    assert_eq!(
+        run("enum Shape { Circle(r), Rect(w, h) }; variant(Rect(1, 1))"),
+        "\"Rect\""
+    );
+    assert!(run_err("enum E { A(x) }; E.B").contains("AttributeError"));
+}
+
+#[test]
+fn enum_equality() {
+    assert_eq!(
+        run("enum Shape { Circle(r), Rect(w, h) }; Circle(1) == Circle(1)"),
+        "true"
+    );
+    assert_eq!(
+        run("enum Shape { Circle(r), Rect(w, h) }; Circle(1) == Rect(1, 1)"),
+        "false"
+    );
+}
+
+#[test]
+fn enum_methods() {
+    assert_eq!(
+        run("enum Shape {
+                Circle(r)
+                Rect(w, h)
+                area() -> match self { Circle(r) -> 3 * r ** 2, Rect(w, h) -> w * h }
+            }
+            Circle(2).area() + Rect(2, 3).area()"),
+        "18"
+    );
+}
+
+#[test]
+fn enum_patterns() {
+    assert_eq!(
+        run("enum R { Ok(value), Err(message) }; match Ok(1) { Ok(v) -> v, Err(e) -> e }"),
+        "1"
+    );
+    assert_eq!(
+        run(
+            "enum R { Ok(value), Err(message) }; match Err(\"no\") { R.Ok(v) -> v, R.Err(e) -> e }"
+        ),
+        "\"no\""
+    );
+    assert_eq!(run("enum R { Ok(value), Err(message) }; match Err(1) { r: R -> \"result\", _ -> \"other\" }"), "\"result\"");
+    assert!(run_err("enum E { A(x) }; match A(1) { A(x, y) -> 0 }").contains("MatchError"));
+}
+
+#[test]
+fn struct_patterns() {
+    assert_eq!(
+        run("struct Point { x, y }; match Point(1, 2) { Point(x, 2) -> x, _ -> 0 }"),
+        "1"
+    );
+    assert_eq!(
+        run("struct Point { x, y }; match Point(1, 3) { Point(x, 2) -> x, _ -> 0 }"),
+        "0"
+    );
+}
+
+#[test]
+fn generators() {
+    assert_eq!(run("g = () -> { yield 1; yield 2 }; list(g())"), "[1, 2]");
+    assert_eq!(
+        run("naturals = () -> { n = 0; loop { yield n; n = n + 1 } }
+            naturals() |> filter((x) -> x % 2 == 0) |> map((x) -> x * x) |> take(4) |> list"),
+        "[0, 4, 16, 36]"
+    );
+    assert!(run_err("f = () -> [yield 1]; f()").contains("ParseError"));
+}
+
+#[test]
+fn yield_in_nested_statements() {
+    assert_eq!(
+        run("g = (xs) -> {
+                for x in xs {
+                    if x > 1 { yield x } else { yield -x }
+                    match x { 3 -> yield \"three\", _ -> nil }
+                }
+                return
+                yield 100
+            }
+            list(g(1..4))"),
+        "[-1, 2, 3, \"three\"]"
+    );
+}
+
+#[test]
+fn for_loops() {
+    assert_eq!(
+        run("out = []; for c in \"aé\" { out.push(c) }; out"),
+        "[\"a\", \"é\"]"
+    );
+    assert_eq!(
+        run("out = []; for i in 0..2 { out.push(i) }; out"),
+        "[0, 1]"
+    );
+    assert_eq!(
+        run("out = []; for k, v in {\"x\" -> 1} { out.push([k, v]) }; out"),
+        "[[\"x\", 1]]"
+    );
+    assert_eq!(
+        run("s = 0; for i, x in enumerate([10, 20]) { s = s + i * x }; s"),
+        "20"
+    );
+    assert!(run_err("for x in 1 { x }").contains("TypeError"));
+}
+
+#[test]
+fn iter_protocol() {
+    assert_eq!(
+        run("struct Bag { items; __iter__() -> { for x in self.items { yield x * 10 } } }; list(Bag([1, 2]))"),
+        "[10, 20]"
+    );
+}
+
+#[test]
+fn next() {
+    assert_eq!(run("it = iter([1]); next(it)"), "1");
+    assert_eq!(run("it = iter([]); next(it, \"done\")"), "\"done\"");
+    assert_eq!(run("type(iter([]))"), "\"iterator\"");
+    assert!(run_err("next(iter([]))").contains("ValueError"));
+}
+
+#[test]
+fn iterator_functions() {
+    assert_eq!(run("reduce(1..=10, (a, b) -> a + b, 0)"), "55");
+    assert_eq!(
+        run("list(zip(count(5, -1), \"ab\"))"),
+        "[[5, \"a\"], [4, \"b\"]]"
+    );
+    assert_eq!(run("list(skip(0..5, 3))"), "[3, 4]");
+}
+
+#[test]
+fn resume() {
+    assert_eq!(
+        run("acc = () -> {
+                total = 0
+                loop {
+                    x = yield total
+                    total += x
+                }
+            }
+            g = acc()
+            [next(g), resume(g, 2), resume(g, 3)]"),
+        "[0, 2, 5]"
+    );
+    assert!(run_err("g = () -> { yield 1 }; x = g(); next(x); resume(x)").contains("ValueError"));
+}
+
+#[test]
+fn tasks() {
+    let mut rt = Runtime::new();
+    rt.evaluate(
+        "struct Log { items }
+        log = Log([])
+        busy = (name) -> { log.items.push(name + \"1\"); yield; log.items.push(name + \"2\") }
+        spawn(busy(\"a\")); spawn(busy(\"b\"))",
+    )
+    .unwrap();
+    assert_eq!(rt.task_count(), 2);
+    rt.run_tasks().unwrap();
+    assert_eq!(rt.task_count(), 0);
+    assert_eq!(
+        rt.evaluate("log.items").unwrap().to_string(),
+        "[\"a1\", \"b1\", \"a2\", \"b2\"]"
+    );
+    assert!(run_err("spawn([1])").contains("TypeError"));
+}
+
+#[test]
+fn sleeping_tasks() {
+    let mut rt = Runtime::new();
+    rt.evaluate("struct Log { items }; log = Log([]); t = () -> { await sleep(2); log.items.push(now()) }; spawn(t())")
+        .unwrap();
+    rt.run_tasks().unwrap();
+    assert_eq!(rt.next_wakeup(), Some(2.0));
+    rt.advance_time(1.0).unwrap();
+    assert_eq!(rt.task_count(), 1);
+    rt.advance_time(1.0).unwrap();
+    assert_eq!(rt.task_count(), 0);
+    assert_eq!(rt.evaluate("log.items").unwrap().to_string(), "[2.0]");
+}
+
+#[test]
+fn channels() {
+    let mut rt = Runtime::new();
+    rt.evaluate(
+        "struct Log { items }
+        log = Log([])
+        replies = channel()
+        listener = () -> { msg = await replies.recv(); log.items.push(msg) }
+        spawn(listener())",
+    )
+    .unwrap();
+    rt.run_tasks().unwrap();
+    assert_eq!(rt.task_count(), 1);
+    let replies = rt.env.get("replies").unwrap();
+    rt.send(&replies, Object::Str("hello".into())).unwrap();
+    rt.run_tasks().unwrap();
+    assert_eq!(rt.task_count(), 0);
+    assert_eq!(rt.evaluate("log.items").unwrap().to_string(), "[\"hello\"]");
+}
+
+#[test]
+fn tasks_belong_to_runtime() {
+    let mut rt = Runtime::new();
+    rt.evaluate("t = () -> { await sleep(2) }; spawn(t())")
+        .unwrap();
+    let mut other = Runtime::new();
+    other.advance_time(5.0).unwrap();
+    assert_eq!((other.task_count(), other.next_wakeup()), (0, None));
+    assert_eq!(other.evaluate("now()").unwrap().to_string(), "5.0");
+    assert_eq!(rt.evaluate("now()").unwrap().to_string(), "0.0");
+    assert_eq!(rt.task_count(), 1);
+}
+
+#[test]
+fn task_errors() {
+    let mut rt = Runtime::new();
+    rt.evaluate("fail = () -> { await sleep(0); 1 / 0 }; spawn(fail())")
+        .unwrap();
+    assert!(rt.run_tasks().unwrap_err().contains("DivideByZero"));
+}
+
+#[test]
+fn sleep_duration_range() {
+    assert!(run_err("sleep(-1)").contains("ValueError"));
+    assert!(run_err("sleep(float(\"nan\"))").contains("ValueError"));
+    assert!(run_err("sleep(float(\"inf\"))").contains("ValueError"));
+    assert!(run_err("sleep(1e300)").contains("ValueError"));
+    let mut rt = Runtime::new();
+    rt.evaluate("t = () -> { await sleep(1e300) }; spawn(t())")
+        .unwrap();
+    assert!(rt.run_tasks().unwrap_err().contains("ValueError"));
+}
+
+#[test]
+fn tail_calls() {
+    // without elimination, even 10000 nested calls overflow the stack
+    assert_eq!(
+        run(
+            "countdown = (n, acc) -> if n == 0 then acc else countdown(n - 1, acc + 1)
+            countdown(100000, 0)"
+        ),
+        "100000"
+    );
+}
+
+#[test]
+fn mutual_tail_calls() {
+    assert_eq!(
+        run(
+            "is_even = (n) -> { if n == 0 { return true }; return is_odd(n - 1) }
+            is_odd = (n) -> match n { 0 -> false, _ -> is_even(n - 1) }
+            is_even(20001)"
+        ),
+        "false"
+    );
+}
+
+#[test]
+fn calls_not_at_tail_position() {
+    assert_eq!(
+        run("fib = (n) -> if n <= 2 then n else fib(n - 1) + fib(n - 2); fib(15)"),
+        "987"
+    );
+    assert_eq!(
+        run("double = (n) -> n * 2; f = (n) -> { { return double(n) }; 5 }; f(1)"),
+        "5"
+    );
+    assert_eq!(run("double = (n) -> n * 2; double(double(2))"), "8");
+}
+
+#[test]
+fn tail_call_defaults() {
+    // default values are evaluated in the definition scope for tail calls too
+    assert_eq!(run("x = 5; f = (y=x) -> y; g = (x) -> f(); g(2)"), "5");
+}
+
+#[test]
+fn list_comprehensions() {
+    assert_eq!(run("[x * x for x in [-1, 2, 3] if x > 0]"), "[4, 9]");
+    assert_eq!(
+        run("[[x, y] for x in 0..3 for y in 0..x if x + y != 3]"),
+        "[[1, 0], [2, 0]]"
+    );
+}
+
+#[test]
+fn hash_comprehensions() {
+    assert_eq!(
+        run("{k -> v * 10 for k, v in {\"a\" -> 1, \"b\" -> 2}}"),
+        "{\"a\" -> 10, \"b\" -> 20}"
+    );
+    assert_eq!(
+        run("{c -> i for i, c in enumerate(\"ab\")}"),
+        "{\"a\" -> 0, \"b\" -> 1}"
+    );
+    assert!(run_err("{[x] -> x for x in 0..2}").contains("TypeError"));
+}
+
+#[test]
+fn comprehension_scope() {
+    // variables of comprehensions do not leak
+    assert_eq!(run("x = 5; ys = [x for x in 0..2]; x"), "5");
+}
+
+#[test]
+fn sets() {
+    assert_eq!(run("{1, 2} == {2, 1}"), "true");
+    assert_eq!(run("set([1, 1, 2])"), "{1, 2}");
+    assert_eq!(run("set()"), "set()");
+    assert_eq!(run("type({1,})"), "\"set\"");
+    assert_eq!(run("2 in {1, 2}"), "true");
+    assert!(run_err("{[1], 2}").contains("TypeError"));
+}
+
+#[test]
+fn set_operators() {
+    assert_eq!(run("{1, 2, 3} | {3, 4,}"), "{1, 2, 3, 4}");
+    assert_eq!(run("{1, 2, 3} & {3, 4}"), "{3}");
+    assert_eq!(run("{1, 2, 3} - {3, 4}"), "{1, 2}");
+    assert_eq!(run("{1, 2, 3} ^ {3, 4}"), "{1, 2, 4}");
+}
+
+#[test]
+fn set_methods() {
+    assert_eq!(
+        run("s = set(); s.add(5); s.add(5); s.add((1, 2)); s.remove(5); s"),
+        "{(1, 2)}"
+    );
+    assert_eq!(run("s = {1, 2}; s.add(3); len(s)"), "3");
+    assert!(run_err("s = {1, 2}; s.remove(3)").contains("KeyError"));
+}
+
+#[test]
+fn tuples() {
+    assert_eq!(run("(1, \"x\")"), "(1, \"x\")");
+    assert_eq!(run("(1, \"x\")[1]"), "\"x\"");
+    assert_eq!(run("(1,)"), "(1,)");
+    assert_eq!(run("(1 + 2)"), "3");
+    assert_eq!(run("tuple()"), "()");
+    assert_eq!(run("tuple(\"ab\")"), "(\"a\", \"b\")");
+    assert_eq!(run("(1, \"x\") + (2,)"), "(1, \"x\", 2)");
+    assert_eq!(run("type((1, 2))"), "\"tuple\"");
+    assert_eq!(run("len((1, 2))"), "2");
+    assert!(run_err("t = (1, 2); t[0] = 3").contains("TypeError"));
+}
+
+#[test]
+fn tuple_comparison() {
+    assert_eq!(run("(1, 2) < (1, 3)"), "true");
+    assert_eq!(run("(1, 2) == (1, 2)"), "true");
+    assert_eq!(run("(1, 2) == [1, 2]"), "false");
+}
+
+#[test]
+fn tuple_keys() {
+    assert_eq!(run("h = {(1, 2) -> \"p\"}; h[(1, 2)]"), "\"p\"");
+    assert_eq!(run("h = {}; h[(3, 4)] = \"q\"; (3, 4) in h"), "true");
+    assert!(run_err("{([1],) -> 1}").contains("TypeError"));
+}
+
+#[test]
+fn hash_keys() {
+    assert_eq!(run("h = {1 -> \"i\"}; h[1]"), "\"i\"");
+    assert_eq!(run("h = {2.5 -> \"f\"}; h[2.5]"), "\"f\"");
+    assert_eq!(run("h = {true -> \"b\"}; h[true]"), "\"b\"");
+    assert_eq!(run("h = {nil -> \"n\"}; h[nil]"), "\"n\"");
+    assert_eq!(run("h = {(1, \"a\") -> \"t\"}; h[(1, \"a\")]"), "\"t\"");
+    assert!(run_err("{float(\"nan\") -> 1}").contains("ValueError"));
+    assert!(run_err("{{1 -> 2} -> 1}").contains("TypeError"));
+}
+
+#[test]
+fn equal_numbers_are_same_key() {
+    assert_eq!(run("h = {1 -> \"a\"}; h[1.0] = \"b\"; h"), "{1 -> \"b\"}");
+    assert_eq!(run("h = {1 -> \"a\"}; 1.0 in h"), "true");
+}
+
+#[test]
+fn hash_insertion_order() {
+    // insertion order is kept on overwrite, and removed keys are appended again
+    assert_eq!(
+        run("h = {3 -> 0, 1 -> 0, 2 -> 0}; h[1] = 5; h.keys()"),
+        "[3, 1, 2]"
+    );
+    assert_eq!(
+        run("h = {3 -> 0, 1 -> 0, 2 -> 0}; h.remove(3); h[3] = 6; h.keys()"),
+        "[1, 2, 3]"
+    );
+    assert_eq!(
+        run("{x % 3 -> x for x in 0..5}"),
+        "{0 -> 3, 1 -> 4, 2 -> 2}"
+    );
+}
+
+#[test]
+fn copy_on_write() {
+    // containers behave as values although their storage is shared
+    assert_eq!(run("a = [1]; b = a; b.push(2); [a, b]"), "[[1], [1, 2]]");
+    assert_eq!(
+        run("a = [1, [2]]; b = a; b[1].push(3); [a, b]"),
+        "[[1, [2]], [1, [2, 3]]]"
+    );
+    assert_eq!(
+        run("h = {\"k\" -> [1]}; g = h; g[\"k\"][0] = 5; [h, g]"),
+        "[{\"k\" -> [1]}, {\"k\" -> [5]}]"
+    );
+    assert_eq!(
+        run("h = {\"k\" -> [1]}; x = h[\"k\"]; x.push(2); [h, x]"),
+        "[{\"k\" -> [1]}, [1, 2]]"
+    );
+    assert_eq!(
+        run("s = \"ab\"; t = s; t += \"c\"; [s, t]"),
+        "[\"ab\", \"abc\"]"
+    );
+}
+
+#[test]
+fn copy_on_write_arguments() {
+    assert_eq!(
+        run("xs = [1]; f = (l) -> { l.push(2); l }; [f(xs), xs]"),
+        "[[1, 2], [1]]"
+    );
+    assert_eq!(
+        run("h = {}; f = (d) -> { d[\"k\"] = 1; d }; [f(h), h]"),
+        "[{\"k\" -> 1}, {}]"
+    );
+}
+
+#[test]
+fn copy_on_write_self_reference() {
+    // pushing a container into itself stores a copy rather than a cycle
+    assert_eq!(run("a = [1]; a.push(a); a"), "[1, [1]]");
+    assert_eq!(
+        run("a = [1]; a.push(a); b = a; a[0] = 9; [a, b]"),
+        "[[9, [1]], [1, [1]]]"
+    );
+}
+
+#[test]
+fn failed_modification_keeps_container() {
+    let mut rt = Runtime::new();
+    rt.evaluate("h = {\"k\" -> [1]}").unwrap();
+    assert!(rt
+        .evaluate("h[\"k\"][5] = 0")
+        .unwrap_err()
+        .contains("IndexOutOfRange"));
+    assert!(rt
+        .evaluate("h[\"k\"].remove(5)")
+        .unwrap_err()
+        .contains("IndexOutOfRange"));
+    assert_eq!(rt.evaluate("h").unwrap().to_string(), "{\"k\" -> [1]}");
+}
+
+#[test]
+fn modification_in_place() {
+    // indexing and pushing do not copy the list
+    assert_eq!(
+        run(
+            "xs = []; for i in 0..20000 { xs.push(i) }; s = 0; for i in 0..20000 { s += xs[i] }; s"
+        ),
+        "199990000"
+    );
+}
+
+#[test]
+fn garbage_collection() {
+    // self-referential instances and closures which refer to their own scope
+    assert_eq!(
+        run("struct Node { next }; for i in 0..10 { n = Node(nil); n.next = n }; gc()"),
+        "10"
+    );
+    assert_eq!(
+        run("struct Node { next }; n = Node(nil); n.next = n; gc()"),
+        "0"
+    );
+    assert_eq!(run("gc()"), "0");
+}
+
+#[test]
+fn garbage_collection_of_closures() {
+    let mut rt = Runtime::new();
+    rt.evaluate("make = () -> { f = (n) -> if n == 0 { 0 } else { f(n - 1) }; f }")
+        .unwrap();
+    // each call leaves the scope of the call and the block which `f` refers to
+    rt.evaluate("for i in 0..3 { make() }").unwrap();
+    assert_eq!(rt.collect_garbage(), 6);
+    assert_eq!(rt.collect_garbage(), 0);
+}
+
+#[test]
+fn garbage_collection_keeps_reachable_cycles() {
+    let mut rt = Runtime::new();
+    rt.evaluate("make = () -> { f = (n) -> if n == 0 { 0 } else { f(n - 1) }; f }")
+        .unwrap();
+    rt.evaluate("h = make(); struct Box { value }; b = Box(1); b.value = [b]")
+        .unwrap();
+    assert_eq!(rt.collect_garbage(), 0);
+    assert_eq!(
+        rt.evaluate("[h(3), b.value[0] == b]").unwrap().to_string(),
+        "[0, true]"
+    );
+}
+
+#[test]
+fn garbage_collection_per_runtime() {
+    // each runtime collects only its own objects
+    let mut rt = Runtime::new();
+    rt.evaluate("struct Node { next }; for i in 0..4 { n = Node(nil); n.next = n }")
+        .unwrap();
+    let mut other = Runtime::new();
+    assert_eq!(other.collect_garbage(), 0);
+    assert_eq!(
+        (other.gc_stats().collections, other.gc_stats().collected),
+        (1, 0)
+    );
+    assert_eq!(rt.collect_garbage(), 4);
+}
+
+#[test]
+fn garbage_collection_runs_automatically() {
+    // the number of tracked objects stays bounded
+    let mut rt = Runtime::new();
+    rt.evaluate("make = () -> { f = (n) -> if n == 0 { 0 } else { f(n - 1) }; f }")
+        .unwrap();
+    rt.evaluate("struct Box { value }").unwrap();
+    let before = rt.gc_stats();
+    rt.evaluate("for i in 0..30000 { make(); n = Box(nil); n.value = n }")
+        .unwrap();
+    let after = rt.gc_stats();
+    assert!(after.collections > before.collections);
+    assert!(after.collected >= before.collected + 30000);
+    assert!(after.tracked < 30000);
+}
+
+#[test]
+fn shared_strings() {
+    assert_eq!(run("s = \"héllo\"; t = s; t[1]"), "\"é\"");
+    assert_eq!(run("s = \"héllo\"; t = s; [len(t), s == t]"), "[5, true]");
+    assert_eq!(
+        run("s = \"héllo\"; t = s; [s + \"!\", t.upper()]"),
+        "[\"héllo!\", \"HÉLLO\"]"
+    );
+    assert_eq!(
+        run("s = \"\"; [len(s), s == \"\", s + s]"),
+        "[0, true, \"\"]"
+    );
+}
+
+#[test]
+fn string_indexing_in_loops() {
+    assert_eq!(
+        run("s = \"\"; for i in 0..3000 { s += \"あ\" }; n = 0; for i in 0..3000 { if s[i] == \"あ\" { n += 1 } }; n"),
+        "3000"
+    );
+}
+
+#[test]
+fn host_strings() {
+    let mut rt = Runtime::new();
+    let greeting = rt.evaluate("\"こんにちは\"").unwrap();
+    assert_eq!(greeting, Object::Str(tinyterp::Str::from("こんにちは")));
+}
+
+#[test]
+fn interned_strings() {
+    // interned names are shared while they are alive, and can be interned again after dropped
+    let name = tinyterp::Str::intern("greeting");
+    assert_eq!(
+        name.as_str().as_ptr(),
+        tinyterp::Str::intern("greeting").as_str().as_ptr()
+    );
+    drop(name);
+    for i in 0..1000 {
+        tinyterp::Str::intern(&format!("name{}", i));
+    }
+    assert_eq!(tinyterp::Str::intern("greeting").as_str(), "greeting");
+}
+
+#[test]
+fn unicode_strings() {
+    // strings are indexed by characters, not by bytes
+    assert_eq!(run("len(\"日本語テキスト\")"), "7");
+    assert_eq!(run("len(\"👍🏽\")"), "2");
+    assert_eq!(
+        run("s = \"日本語テキスト\"; [s[0], s[6]]"),
+        "[\"日\", \"ト\"]"
+    );
+    assert_eq!(run("list(\"日本語\")[1]"), "\"本\"");
+    assert_eq!(run("reverse(\"日本語\")"), "\"語本日\"");
+}
+
+#[test]
+fn slices() {
+    assert_eq!(
+        run("s = \"日本語テキスト\"; [s[2..4], s[5..=6]]"),
+        "[\"語テ\", \"スト\"]"
+    );
+    assert_eq!(run("[1, 2, 3][1..3]"), "[2, 3]");
+    assert_eq!(run("(1, 2, 3)[0..1]"), "(1,)");
+    assert_eq!(run("[[1, 2][2..2], \"日本\"[2..2]]"), "[[], \"\"]");
+    assert_eq!(run("\"abc\"[2..0]"), "\"\"");
+    assert_eq!(run("[reverse([1, 2]), reverse(\"\")]"), "[[2, 1], \"\"]");
+}
+
+#[test]
+fn string_index_errors() {
+    assert!(run_err("\"日本語\"[5]").contains("IndexOutOfRange"));
+    assert!(run_err("\"日本語\"[3]").contains("IndexOutOfRange"));
+    assert!(run_err("\"日本語\"[-1]").contains("IndexOutOfRange"));
+    assert!(run_err("\"日本語\"[1..4]").contains("IndexOutOfRange"));
+    assert!(run_err("[1, 2][-1..1]").contains("IndexOutOfRange"));
+    assert!(run_err("reverse(1)").contains("TypeError"));
+}
+
+#[test]
+fn freeze() {
+    assert_eq!(run("a = freeze([1, [2]]); a"), "[1, [2]]");
+    assert_eq!(
+        run("a = freeze([1, [2]]); [a[1].len(), a == [1, [2]]]"),
+        "[1, true]"
+    );
+    assert_eq!(
+        run("h = freeze({\"k\" -> [3]}); h == {\"k\" -> [3]}"),
+        "true"
+    );
+    assert_eq!(run("freeze(1)"), "1");
+    // the variable can still be bound to another value
+    assert_eq!(run("a = freeze([1]); a = [5]; a.push(6); a"), "[5, 6]");
+}
+
+#[test]
+fn freeze_copies_are_mutable() {
+    assert_eq!(
+        run("a = freeze([1]); b = a + [2]; b.push(3); [a, b]"),
+        "[[1], [1, 2, 3]]"
+    );
+    assert_eq!(run("t = ([1, 2],); t[0].len()"), "2");
+}
+
+#[test]
+fn freeze_errors() {
+    assert!(run_err("a = freeze([1]); a.push(2)").contains("ImmutableError"));
+    assert!(run_err("a = freeze([[1]]); a[0].push(2)").contains("ImmutableError"));
+    assert!(run_err("a = freeze([[1]]); a[0][0] = 2").contains("ImmutableError"));
+    assert!(run_err("a = freeze([[1]]); b = a[0]; b.pop()").contains("ImmutableError"));
+    assert!(
+        run_err("h = freeze({\"a\" -> {\"b\" -> 1}}); h[\"a\"][\"b\"] = 2")
+            .contains("ImmutableError")
+    );
+    assert!(run_err("h = freeze({\"a\" -> 1}); h.remove(\"a\")").contains("ImmutableError"));
+    assert!(run_err("s = freeze(set([1])); s.add(2)").contains("ImmutableError"));
+    assert!(run_err("t = freeze(([1],)); t[0].push(2)").contains("ImmutableError"));
+}
+
+#[test]
+fn failed_modification_keeps_frozen_value() {
+    let mut rt = Runtime::new();
+    assert!(rt.evaluate("a = freeze([[1], 2]); a[0].push(3)").is_err());
+    assert_eq!(rt.evaluate("a").unwrap().to_string(), "[[1], 2]");
+}
+
+#[test]
+fn protected_builtins() {
+    assert!(run_err("len = 1").contains("ImmutableError"));
+    assert!(run_err("pi += 1").contains("ImmutableError"));
+    assert!(run_err("drop max").contains("ImmutableError"));
+    assert!(run_err("struct list {\n x\n}").contains("ImmutableError"));
+    assert_eq!(run("len([1])"), "1");
+}
+
+#[test]
+fn builtins_can_be_shadowed() {
+    // parameters and other local bindings can shadow them
+    assert_eq!(
+        run("f = (list) -> { list = list + [1]; list }; f([0])"),
+        "[0, 1]"
+    );
+    assert_eq!(
+        run("g = (max) -> { for count in 0..3 { max += count }; max }; g(0)"),
+        "3"
+    );
+}
+
+#[test]
+fn protect_host_bindings() {
+    let mut rt = Runtime::new();
+    rt.evaluate("limits = [1, 2]").unwrap();
+    rt.protect("limits");
+    assert_eq!(rt.evaluate("limits.len()").unwrap().to_string(), "2");
+    assert!(rt.evaluate("limits.push(3)").is_err());
+    assert!(rt.evaluate("limits = []").is_err());
+    assert_eq!(rt.evaluate("limits").unwrap().to_string(), "[1, 2]");
+}
+
+#[test]
+fn complex_numbers() {
+    assert_eq!(run("z = 1.5 + 2j; [z, type(z)]"), "[(1.5+2j), \"complex\"]");
+    assert_eq!(run("[0j, 2j, -(1.5 + 2j)]"), "[0j, 2j, (-1.5-2j)]");
+    assert_eq!(run("[complex(1, 2), complex(3 - 4j)]"), "[(1+2j), (3-4j)]");
+    assert_eq!(
+        run("z = 3 - 4j; [re(z), im(z), re(2), im(2.5)]"),
+        "[3.0, -4.0, 2, 0.0]"
+    );
+    assert_eq!(run("z = 3 - 4j; [abs(z), conj(z)]"), "[5.0, (3+4j)]");
+}
+
+#[test]
+fn complex_arithmetic() {
+    assert_eq!(
+        run("z = 1.5 + 2j; [z * z, z / 2]"),
+        "[(-1.75+6j), (0.75+1j)]"
+    );
+    assert_eq!(run("[1 / 1j, 1j ** 2]"), "[-1j, (-1+0j)]");
+    assert_eq!(run("2 ** 1j == exp(ln(2) * 1j)"), "true");
+    assert_eq!(run("1e300j * 1e300j"), "(-inf+0j)");
+}
+
+#[test]
+fn complex_functions() {
+    assert_eq!(run("[sqrt(-4 + 0j), sqrt(4)]"), "[2j, 2.0]");
+    assert_eq!(run("[exp(0j), ln(-1 + 0j) == pi * 1j]"), "[(1+0j), true]");
+}
+
+#[test]
+fn complex_equality() {
+    assert_eq!(run("[1 + 0j == 1, complex(0, 0) == 0]"), "[true, true]");
+    assert_eq!(run("{1 -> \"a\", 1 + 0j -> \"b\"}"), "{1 -> \"b\"}");
+    assert_eq!(run("match 1j { x: complex -> re(x) + im(x) }"), "1.0");
+}
+
+#[test]
+fn complex_errors() {
+    assert!(run_err("1j / 0").contains("DivideByZero"));
+    assert!(run_err("0j ** -1").contains("DivideByZero"));
+    assert!(run_err("1j < 2j").contains("TypeError"));
+    assert!(run_err("1j // 2").contains("TypeError"));
+    assert!(run_err("complex(1j, 1)").contains("TypeError"));
+}